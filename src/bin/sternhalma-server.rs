@@ -1,167 +1,844 @@
 //! # Sternhalma Server Binary
 //!
 //! This is the entry point for the Sternhalma Server application.
-//! It parses command-line arguments, initializes the logger, sets up the server thread channels,
-//! and starts the TCP and WebSocket listeners.
+//! It parses command-line arguments and the configuration file, initializes the logger, recovers journaled games,
+//! creates the default game room, and starts the TCP and HTTP (WebSocket and REST API) listeners,
+//! optionally over TLS.
 //!
 //! ## Usage
 //! ```sh
 //! sternhalma-server --tcp 0.0.0.0:1234 --ws 0.0.0.0:8080
+//! sternhalma-server --config /etc/sternhalma.toml --max-turns 200
+//! sternhalma-server --config /etc/sternhalma.toml --check-config
+//! sternhalma-server --tcp 0.0.0.0:1234 --tls-cert cert.pem --tls-key key.pem
+//! sternhalma-server --unix /run/sternhalma.sock --unix-mode 660
+//! sternhalma-server --tcp 0.0.0.0:1234 --telnet 0.0.0.0:2323
+//! sternhalma-server --engine ./my-engine --engine "./other-engine --depth 3"
+//! sternhalma-server --engine ./my-engine --text-engine ./text-engine --engine-movetime 500
+//! sternhalma-server --tcp 0.0.0.0:1234 --bot greedy:2 --replace-absent search
+//! sternhalma-server --tcp 0.0.0.0:1234 --persistent
+//! sternhalma-server archive list --archive-dir games/ --player alice
 //! ```
 
-use std::time::Duration;
-
-use anyhow::{Context, Result};
-use clap::Parser;
-use tokio::{
-    net::TcpListener,
-    sync::{broadcast, mpsc, oneshot},
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use axum::{Router, routing::get};
-use futures::{SinkExt, StreamExt};
+use anyhow::{Context, Result, anyhow};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use itertools::Itertools;
+use tokio::net::TcpListener;
+
 use sternhalma_server::server::{
-    MainThreadMessage, Server,
-    client::{ClientSink, ClientStream},
-    handshake::{AppState, handle_handshake},
-    messages::{ClientMessage, ServerBroadcast},
-    protocol::ServerCodec,
-    ws::ws_handler,
+    ServerConfig,
+    archive::{self, Archive, ArchiveFilter},
+    bot::{self, BotKind, BotSeat},
+    config::ConfigFile,
+    engine::{self, EngineCommand, EngineProtocol},
+    handshake::{handle_handshake, raw_connection},
+    http::{self, HttpState},
+    metrics::Transport,
+    rooms::Rooms,
+    telnet,
+    tls::{Tls, TlsListener},
 };
-use tokio_util::codec::Framed;
-
-const LOCAL_CHANNEL_CAPACITY: usize = 32;
+use sternhalma_server::sternhalma::board::player::{PLAYER_COUNT, Player};
 
 /// Command line arguments
+///
+/// Settings left out are read from the configuration file, if any, and fall back to their default.
 #[derive(Debug, Parser)]
 #[command(name = "sternhalma-server", version, about)]
 struct Args {
+    /// TOML configuration file, overridden by the command line arguments
+    #[arg(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Validate the configuration file and exit
+    #[arg(long, requires = "config")]
+    check_config: bool,
     /// Host IP address for Raw TCP
     #[arg(long, value_name = "ADDRESS")]
     tcp: Option<String>,
-    /// Host IP address for WebSocket
+    /// Host IP address for WebSocket and the REST API
     #[arg(long, value_name = "ADDRESS")]
     ws: Option<String>,
+    /// Host IP address for the human-readable line protocol, playable with `nc` or `telnet`
+    #[arg(long, value_name = "ADDRESS")]
+    telnet: Option<String>,
+    /// Path of a Unix socket for the raw protocol
+    #[arg(long, value_name = "PATH")]
+    unix: Option<PathBuf>,
+    /// Permissions of the Unix socket, in octal (e.g. `660`)
+    #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+    unix_mode: Option<u32>,
+    /// Engine executable, with its arguments, taking a seat of the default game over stdin/stdout
+    ///
+    /// Repeat to seat a second engine and run a full match.
+    #[arg(long = "engine", value_name = "COMMAND")]
+    engines: Vec<EngineCommand>,
+    /// Engine executable, with its arguments, speaking the text protocol over stdin/stdout
+    ///
+    /// Text engines take their seats after the engines given with `--engine`.
+    #[arg(long = "text-engine", value_name = "COMMAND")]
+    text_engines: Vec<EngineCommand>,
+    /// Milliseconds text engines are given to think about each movement (default: 1000)
+    #[arg(long, value_name = "MILLISECONDS")]
+    engine_movetime: Option<u64>,
+    /// Built-in bot taking a seat of the default game: `random`, `greedy` or `search`
+    ///
+    /// Follow the kind with the seat number to choose the seat (e.g. `greedy:2`).
+    /// Repeat to seat a second bot.
+    #[arg(long = "bot", value_name = "KIND[:SEAT]")]
+    bots: Vec<BotSeat>,
+    /// Built-in bot taking the seat of a player who did not reconnect in time, instead of them forfeiting
+    #[arg(long, value_name = "KIND")]
+    replace_absent: Option<BotKind>,
     /// Maximum number of turns
     #[arg(short = 'n', long, value_name = "N")]
     max_turns: Option<usize>,
-    #[arg(short, long, value_name = "SECONDS", default_value_t = 300)]
-    timeout: u64,
+    /// Seconds to wait for all players to connect (default: 300)
+    #[arg(short, long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+    /// Seconds a disconnected player has to reconnect before forfeiting or losing their seat (default: 60)
+    #[arg(long, value_name = "SECONDS")]
+    disconnect_timeout: Option<u64>,
+    /// Reject chat messages from players
+    #[arg(long, overrides_with = "no_mute_chat")]
+    mute_chat: bool,
+    /// Accept chat messages from players, even if muted by the configuration file
+    #[arg(long, overrides_with = "mute_chat")]
+    no_mute_chat: bool,
+    /// Keep the rooms running once their game is over, for rematches and new games
+    ///
+    /// Players waiting in the lobby of a persistent room are never timed out,
+    /// but rooms created through the REST API close once nobody holds a seat for `--timeout`.
+    #[arg(long, overrides_with = "no_persistent")]
+    persistent: bool,
+    /// Close the rooms once their game is over, even if persistent in the configuration file
+    #[arg(long, overrides_with = "persistent")]
+    no_persistent: bool,
+    /// Directory where games are journaled, to recover unfinished games after a restart
+    #[arg(long, value_name = "PATH")]
+    journal_dir: Option<PathBuf>,
+    /// Directory where finished games are archived
+    #[arg(long, value_name = "PATH")]
+    archive_dir: Option<PathBuf>,
+    /// Seconds to wait for connections to close when shutting down (default: 10)
+    #[arg(long, value_name = "SECONDS")]
+    shutdown_timeout: Option<u64>,
+    /// Maximum number of rooms hosted at once, beyond which the REST API refuses to create rooms
+    #[arg(long, value_name = "N")]
+    max_rooms: Option<usize>,
+    /// PEM file holding the TLS certificate chain, to serve both listeners over TLS
+    #[arg(long, value_name = "PATH")]
+    tls_cert: Option<PathBuf>,
+    /// PEM file holding the private key of the TLS certificate
+    #[arg(long, value_name = "PATH")]
+    tls_key: Option<PathBuf>,
+    /// Log filter, with the syntax of `RUST_LOG` which takes precedence (e.g. `info`)
+    #[arg(long, value_name = "FILTER")]
+    log_level: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::init();
+/// Subcommands
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Browse the archive of finished games
+    #[command(subcommand)]
+    Archive(ArchiveCommand),
+}
 
-    // Parse command line arguments
-    let args = Args::parse();
-    log::debug!("Command line arguments: {args:?}");
-    let timeout = Duration::from_secs(args.timeout);
+/// Archive subcommands
+#[derive(Debug, Subcommand)]
+enum ArchiveCommand {
+    /// List archived games, oldest first
+    List {
+        /// Archive directory
+        #[arg(long, value_name = "PATH")]
+        archive_dir: PathBuf,
+        /// Only games played by a client with this name
+        #[arg(long, value_name = "NAME")]
+        player: Option<String>,
+        /// Only games with this type of result
+        #[arg(long, value_parser = ["finished", "max_turns", "forfeit", "resigned", "draw", "aborted"])]
+        result: Option<String>,
+        /// Only games won by this player
+        #[arg(long)]
+        winner: Option<PlayerArg>,
+        /// Only games that lasted at least this many turns
+        #[arg(long, value_name = "N")]
+        min_turns: Option<usize>,
+        /// Only games finished at or after this time (RFC 3339, e.g. `2025-01-31T12:00:00Z`)
+        #[arg(long, value_name = "TIME", value_parser = humantime::parse_rfc3339_weak)]
+        since: Option<SystemTime>,
+        /// Print the index entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the full record of an archived game
+    Show {
+        /// Archive directory
+        #[arg(long, value_name = "PATH")]
+        archive_dir: PathBuf,
+        /// Identifier of the game
+        id: String,
+    },
+}
+
+/// Player selected on the command line
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PlayerArg {
+    Player1,
+    Player2,
+}
+
+impl From<PlayerArg> for Player {
+    fn from(player: PlayerArg) -> Self {
+        match player {
+            PlayerArg::Player1 => Player::Player1,
+            PlayerArg::Player2 => Player::Player2,
+        }
+    }
+}
 
-    // --- Channel Setup ---
-    // The server architecture relies on message passing between threads/tasks.
+/// Settings of the server, merged from the command line and the configuration file
+#[derive(Debug)]
+struct Settings {
+    tcp: Option<String>,
+    ws: Option<String>,
+    telnet: Option<String>,
+    unix: Option<PathBuf>,
+    unix_mode: Option<u32>,
+    /// Certificate chain and private key
+    tls: Option<(PathBuf, PathBuf)>,
+    /// Engines in the order they take their seats
+    engines: Vec<(EngineCommand, EngineProtocol)>,
+    /// Bots seated in the default room
+    bots: Vec<BotSeat>,
+    /// Configuration of the rooms
+    room: ServerConfig,
+    shutdown_timeout: Duration,
+    max_rooms: Option<usize>,
+    journal_dir: Option<PathBuf>,
+    archive_dir: Option<PathBuf>,
+    log_level: Option<String>,
+}
+
+impl Settings {
+    /// Merges the command line arguments with the configuration file, the former taking precedence
+    ///
+    /// Exits with a usage error if the merged settings are inconsistent.
+    fn merge(args: Args, file: ConfigFile) -> Self {
+        let ConfigFile {
+            listeners,
+            room,
+            limits,
+            engines,
+            bots,
+            persistence,
+            logging,
+        } = file;
 
-    // Client threads -> Server thread
-    // Used for active game actions (move, disconnect)
-    let (client_msg_tx, client_msg_rx) = mpsc::channel::<ClientMessage>(LOCAL_CHANNEL_CAPACITY);
+        let unix = args.unix.or(listeners.unix);
+        let unix_mode = args.unix_mode.or(listeners.unix_mode);
+        if unix_mode.is_some_and(|mode| mode > 0o777) {
+            usage_error(
+                ErrorKind::InvalidValue,
+                "Invalid unix_mode, expected octal permissions such as 0o660",
+            );
+        }
+        if unix_mode.is_some() && unix.is_none() {
+            usage_error(
+                ErrorKind::MissingRequiredArgument,
+                "The Unix socket permissions require a Unix socket path",
+            );
+        }
+        let tls = match (
+            args.tls_cert.or(listeners.tls_cert),
+            args.tls_key.or(listeners.tls_key),
+        ) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => usage_error(
+                ErrorKind::MissingRequiredArgument,
+                "The TLS certificate and private key must be provided together",
+            ),
+        };
 
-    // Server thread -> Client threads
-    // Used for broadcasting common information (move updates, game finish)
-    let (server_broadcast_tx, _server_broadcast_rx) =
-        broadcast::channel::<ServerBroadcast>(LOCAL_CHANNEL_CAPACITY);
+        // Engines given on the command line replace those of the file
+        let (raw, text) = if args.engines.is_empty() && args.text_engines.is_empty() {
+            (engines.raw, engines.text)
+        } else {
+            (args.engines, args.text_engines)
+        };
+        let movetime =
+            Duration::from_millis(args.engine_movetime.or(engines.movetime).unwrap_or(1000));
+        let engines = raw
+            .into_iter()
+            .map(|command| (command, EngineProtocol::Raw))
+            .chain(
+                text.into_iter()
+                    .map(|command| (command, EngineProtocol::Text { movetime })),
+            )
+            .collect::<Vec<_>>();
 
-    // Main thread -> Server thread
-    // Used for connection establishment (handshake requests)
-    let (main_tx, main_rx) = mpsc::channel::<MainThreadMessage>(LOCAL_CHANNEL_CAPACITY);
+        // Bots given on the command line replace those of the file
+        let seats = if args.bots.is_empty() {
+            bots.seats
+        } else {
+            args.bots
+        };
+        if seats
+            .iter()
+            .filter_map(|seat| seat.player)
+            .duplicates()
+            .next()
+            .is_some()
+        {
+            usage_error(
+                ErrorKind::ArgumentConflict,
+                "Two bots cannot take the same seat",
+            );
+        }
 
-    // Channel for the server thread to send shutdown signal to main thread
-    // If the server logic fails or finishes, it triggers a full application shutdown.
-    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let settings = Self {
+            tcp: args.tcp.or(listeners.tcp),
+            ws: args.ws.or(listeners.ws),
+            telnet: args.telnet.or(listeners.telnet),
+            unix,
+            unix_mode,
+            tls,
+            engines,
+            bots: seats,
+            room: ServerConfig {
+                connection_timeout: Duration::from_secs(
+                    args.timeout.or(limits.timeout).unwrap_or(300),
+                ),
+                max_turns: args.max_turns.or(room.max_turns).unwrap_or(usize::MAX),
+                disconnect_timeout: Duration::from_secs(
+                    args.disconnect_timeout
+                        .or(limits.disconnect_timeout)
+                        .unwrap_or(60),
+                ),
+                mute_chat: switch(args.mute_chat, args.no_mute_chat)
+                    .or(room.mute_chat)
+                    .unwrap_or(false),
+                replace_absent: args.replace_absent.or(bots.replace_absent),
+                persistent: switch(args.persistent, args.no_persistent)
+                    .or(room.persistent)
+                    .unwrap_or(false),
+            },
+            shutdown_timeout: Duration::from_secs(
+                args.shutdown_timeout
+                    .or(limits.shutdown_timeout)
+                    .unwrap_or(10),
+            ),
+            max_rooms: args.max_rooms.or(limits.max_rooms),
+            journal_dir: args.journal_dir.or(persistence.journal_dir),
+            archive_dir: args.archive_dir.or(persistence.archive_dir),
+            log_level: args.log_level.or(logging.level),
+        };
 
-    // --- Spawn Game Server ---
-    // The `Server` struct runs in its own task and manages the game logic.
-    let server = Server::new(main_rx, client_msg_rx, server_broadcast_tx.clone())
-        .with_context(|| "Failed to create server")?;
+        if settings.tcp.is_none()
+            && settings.ws.is_none()
+            && settings.unix.is_none()
+            && settings.telnet.is_none()
+            && settings.engines.is_empty()
+            && settings.bots.is_empty()
+        {
+            usage_error(
+                ErrorKind::MissingRequiredArgument,
+                "One of --tcp, --ws, --unix, --telnet, --engine, --text-engine or --bot must be provided",
+            );
+        }
+        if settings.engines.len() + settings.bots.len() > PLAYER_COUNT {
+            usage_error(
+                ErrorKind::TooManyValues,
+                format!("At most {PLAYER_COUNT} engines and bots can play a game"),
+            );
+        }
+        settings
+    }
+}
+
+/// Reads a pair of flags turning a setting on or off, of which only the last one given is kept
+///
+/// Returns `None` when neither is given.
+fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        (false, false) => None,
+    }
+}
 
-    let max_turns = args.max_turns.unwrap_or(usize::MAX);
+/// Exits with a usage error
+fn usage_error(kind: ErrorKind, message: impl std::fmt::Display) -> ! {
+    Args::command().error(kind, message).exit()
+}
+
+/// Waits for a signal asking the server to shut down
+///
+/// Returns the name of the received signal.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {e:?}");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    }
+}
+
+/// Parses file permissions written in octal
+fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or(format!(
+            "Invalid mode {mode}, expected octal permissions such as 660"
+        ))
+}
+
+/// Binds a Unix socket, replacing the socket file left behind by a previous server
+///
+/// With permissions, the socket is bound in a private directory and moved into place once they are set,
+/// so that it is never reachable with the default permissions.
+#[cfg(unix)]
+fn bind_unix(path: &Path, mode: Option<u32>) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!("{} exists and is not a socket", path.display()));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(anyhow!("{} is in use by another server", path.display()));
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let Some(mode) = mode else {
+        return tokio::net::UnixListener::bind(path)
+            .with_context(|| format!("Failed to bind Unix socket {}", path.display()));
+    };
 
+    // The private directory sits next to the socket, so that it is moved within the same file system
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow!("Invalid socket path {}", path.display()))?;
+    let dir = path.with_file_name(format!(
+        ".{name}.{pid}",
+        name = file_name.to_string_lossy(),
+        pid = std::process::id()
+    ));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    let staged = dir.join("socket");
+    let bound = tokio::net::UnixListener::bind(&staged)
+        .with_context(|| format!("Failed to bind Unix socket {}", path.display()))
+        .and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+            std::fs::rename(&staged, path)
+                .with_context(|| format!("Failed to move Unix socket to {}", path.display()))?;
+            Ok(listener)
+        });
+    // The socket is left behind in the directory if it could not be moved
+    let _ = std::fs::remove_file(&staged);
+    if let Err(e) = std::fs::remove_dir(&dir) {
+        log::warn!("Failed to remove directory {}: {e:?}", dir.display());
+    }
+    bound
+}
+
+/// Reloads the TLS certificate every time the server receives `SIGHUP`
+#[cfg(unix)]
+fn reload_on_hangup(tls: Arc<Tls>) -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hangup = signal(SignalKind::hangup()).with_context(|| "Failed to listen for SIGHUP")?;
     tokio::spawn(async move {
-        if let Err(e) = server.try_run(timeout, max_turns).await {
-            log::error!("Server encountered an error: {e:?}");
+        while hangup.recv().await.is_some() {
+            match tls.reload() {
+                Ok(()) => log::info!("Reloaded TLS certificate"),
+                Err(e) => log::error!("Failed to reload TLS certificate: {e:?}"),
+            }
         }
-        log::trace!("Sending shutdown signal");
-        let _ = shutdown_tx.send(());
     });
+    Ok(())
+}
 
-    // App State held by connection handlers
-    let app_state = AppState {
-        main_tx: main_tx.clone(),
-        client_msg_tx,
-        server_broadcast_tx,
+/// Runs an archive subcommand
+fn run_archive_command(command: ArchiveCommand) -> Result<()> {
+    match command {
+        ArchiveCommand::List {
+            archive_dir,
+            player,
+            result,
+            winner,
+            min_turns,
+            since,
+            json,
+        } => {
+            let filter = ArchiveFilter {
+                player,
+                result,
+                winner: winner.map(Player::from),
+                min_turns,
+                since: since.map(|time| {
+                    time.duration_since(UNIX_EPOCH)
+                        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+                }),
+            };
+            let entries = archive::read_index(&archive_dir)?;
+            for entry in entries.iter().filter(|entry| filter.matches(entry)) {
+                if json {
+                    println!("{}", serde_json::to_string(entry)?);
+                    continue;
+                }
+                let finished_at = UNIX_EPOCH + Duration::from_millis(entry.finished_at);
+                let players = entry
+                    .seats
+                    .iter()
+                    .map(|seat| seat.name.as_deref().unwrap_or("<unnamed>"))
+                    .collect::<Vec<_>>()
+                    .join(" vs ");
+                let winner = entry
+                    .winner
+                    .map_or("-".to_string(), |winner| winner.to_string());
+                println!(
+                    "{id}  {finished_at}  {result:<9}  {winner:<14}  {turns:>4} turns  {scores:?}  {players}",
+                    id = entry.id,
+                    finished_at = humantime::format_rfc3339_seconds(finished_at),
+                    result = entry.result,
+                    turns = entry.total_turns,
+                    scores = entry.scores,
+                );
+            }
+            Ok(())
+        }
+        ArchiveCommand::Show { archive_dir, id } => {
+            let entry = archive::read_index(&archive_dir)?
+                .into_iter()
+                .find(|entry| entry.id.to_string() == id)
+                .ok_or(anyhow!("Game {id} not found in {}", archive_dir.display()))?;
+            let game = archive::read_game(Path::new(&archive_dir), &entry)?;
+            println!("{}", serde_json::to_string_pretty(&game)?);
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Parse command line arguments
+    let mut args = Args::parse();
+
+    if let Some(Command::Archive(command)) = args.command.take() {
+        env_logger::init();
+        return run_archive_command(command);
+    }
+
+    // Merge the configuration file, overridden by the command line
+    let file = match &args.config {
+        Some(path) => ConfigFile::load(path)?,
+        None => ConfigFile::default(),
+    };
+    let check_config = args.check_config;
+    let settings = Settings::merge(args, file);
+
+    // Initialize logger, `RUST_LOG` taking precedence over the configured filter
+    let mut env = env_logger::Env::default();
+    if let Some(level) = &settings.log_level {
+        env = env.default_filter_or(level);
+    }
+    env_logger::Builder::from_env(env).init();
+    log::debug!("Settings: {settings:?}");
+
+    // The certificate is loaded before the configuration is reported valid, to check it as well
+    let tls = match &settings.tls {
+        Some((cert, key)) => Some(Arc::new(
+            Tls::load(cert, key).with_context(|| "Failed to load TLS certificate")?,
+        )),
+        None => None,
+    };
+
+    if check_config {
+        println!("Configuration is valid");
+        return Ok(());
+    }
+
+    // --- Spawn Game Server ---
+    // Every room runs its own `Server` task that manages the game logic.
+    // The room configured on the command line is joined by connections that do not name one,
+    // and the application shuts down once its server finishes.
+    let config = settings.room.clone();
+    let mut rooms = Rooms::new();
+    if let Some(dir) = &settings.journal_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create journal directory {}", dir.display()))?;
+        rooms = rooms.with_journal_dir(dir);
+    }
+    if let Some(dir) = &settings.archive_dir {
+        rooms = rooms.with_archive(Archive::open(dir)?);
+    }
+    if let Some(max_rooms) = settings.max_rooms {
+        rooms = rooms.with_max_rooms(max_rooms);
+    }
+    // Unfinished games are resumed, the default room included
+    let recovered = rooms
+        .recover()
+        .with_context(|| "Failed to recover journaled games")?;
+    let default_recovered = recovered.is_some();
+    let (default_room, server_handle) = match recovered {
+        Some(default_room) => default_room,
+        None => rooms
+            .create_default(config.clone())
+            .with_context(|| "Failed to create default room")?,
     };
 
+    // App State held by connection handlers
+    let app_state = rooms
+        .get(&default_room)
+        .expect("Default room was just created")
+        .app_state;
+
+    // Bots take their seats before the listeners start, so that clients cannot take the seats chosen for them
+    // The bots of a recovered default room are already back in their journaled seats
+    if default_recovered {
+        if !settings.bots.is_empty() {
+            log::warn!(
+                "Default room was recovered, its journaled bots keep their seats instead of the configured ones"
+            );
+        }
+    } else {
+        for seat in &settings.bots {
+            bot::start(*seat, app_state.clone())
+                .await
+                .with_context(|| format!("Failed to seat {kind} bot", kind = seat.kind))?;
+        }
+    }
+
     // --- Start Listener ---
 
-    if args.tcp.is_none() && args.ws.is_none() {
-        use clap::CommandFactory;
-        let mut cmd = Args::command();
-        cmd.error(
-            clap::error::ErrorKind::MissingRequiredArgument,
-            "Either --tcp or --ws must be provided",
-        )
-        .exit();
+    // Both listeners share the certificate, reloaded on SIGHUP
+    #[cfg(unix)]
+    if let Some(tls) = &tls {
+        reload_on_hangup(tls.clone())?;
     }
 
-    if let Some(addr) = args.tcp {
+    if let Some(addr) = settings.tcp {
         // 1. TCP Listener (Raw protocol)
         let listener = TcpListener::bind(&addr)
             .await
             .with_context(|| "Failed to bind listener to socket")?;
-        log::info!("Listening (TCP) at {addr}");
+        log::info!(
+            "Listening (TCP{}) at {addr}",
+            if tls.is_some() { "/TLS" } else { "" }
+        );
 
         let app_state = app_state.clone();
+        let tls = tls.clone();
+        let shutdown = rooms.shutdown_token();
         tokio::spawn(async move {
             loop {
-                match listener.accept().await {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    _ = shutdown.cancelled() => {
+                        log::info!("Stopped accepting TCP connections");
+                        break;
+                    }
+                };
+                match accepted {
                     Err(e) => {
                         log::error!("Failed to accept connection: {e:?}");
                         continue;
                     }
-                    Ok((stream, _addr)) => {
-                        let framed = Framed::new(stream, ServerCodec::new());
-                        let (write, read) = framed.split();
-                        let sink: ClientSink = Box::pin(write.sink_map_err(|e| anyhow::anyhow!(e)));
-                        let stream: ClientStream =
-                            Box::pin(read.map(|msg| msg.map_err(|e| anyhow::anyhow!(e))));
+                    Ok((stream, addr)) => {
+                        let app_state = app_state.clone();
+                        let tls = tls.clone();
+                        tokio::spawn(async move {
+                            let (stream, sink) = match tls {
+                                None => raw_connection(stream),
+                                Some(tls) => match tls.accept(stream).await {
+                                    Ok(stream) => raw_connection(stream),
+                                    Err(e) => {
+                                        log::warn!("Rejected connection from {addr}: {e:?}");
+                                        return;
+                                    }
+                                },
+                            };
+                            handle_handshake(stream, sink, app_state, Transport::Tcp).await;
+                        });
+                    }
+                }
+            }
+        });
+    }
+
+    if let Some(path) = &settings.unix {
+        // 2. Unix Listener (Raw protocol, local clients)
+        #[cfg(not(unix))]
+        return Err(anyhow!("Unix sockets are not supported on this platform"));
+        #[cfg(unix)]
+        {
+            let listener = bind_unix(path, settings.unix_mode)?;
+            log::info!("Listening (Unix) at {}", path.display());
+
+            let app_state = app_state.clone();
+            let shutdown = rooms.shutdown_token();
+            tokio::spawn(async move {
+                loop {
+                    let accepted = tokio::select! {
+                        accepted = listener.accept() => accepted,
+                        _ = shutdown.cancelled() => {
+                            log::info!("Stopped accepting Unix connections");
+                            break;
+                        }
+                    };
+                    match accepted {
+                        Err(e) => {
+                            log::error!("Failed to accept connection: {e:?}");
+                            continue;
+                        }
+                        Ok((stream, _addr)) => {
+                            let (stream, sink) = raw_connection(stream);
+                            tokio::spawn(handle_handshake(
+                                stream,
+                                sink,
+                                app_state.clone(),
+                                Transport::Unix,
+                            ));
+                        }
+                    }
+                }
+            });
+        }
+    }
 
-                        tokio::spawn(handle_handshake(stream, sink, app_state.clone()));
+    if let Some(addr) = &settings.telnet {
+        // 3. Telnet Listener (Human-readable line protocol)
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| "Failed to bind telnet listener to socket")?;
+        log::info!("Listening (Telnet) at {addr}");
+
+        let app_state = app_state.clone();
+        let shutdown = rooms.shutdown_token();
+        tokio::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    _ = shutdown.cancelled() => {
+                        log::info!("Stopped accepting telnet connections");
+                        break;
+                    }
+                };
+                match accepted {
+                    Err(e) => {
+                        log::error!("Failed to accept connection: {e:?}");
+                        continue;
+                    }
+                    Ok((stream, _addr)) => {
+                        let (read, write) = stream.into_split();
+                        let (stream, sink) = telnet::connection(read, write);
+                        tokio::spawn(handle_handshake(
+                            stream,
+                            sink,
+                            app_state.clone(),
+                            Transport::Telnet,
+                        ));
                     }
                 }
             }
         });
     }
 
-    if let Some(addr) = args.ws {
-        // 2. WebSocket Listener (Web Clients)
-        let app = Router::new()
-            .route("/ws", get(ws_handler))
-            .layer(tower_http::cors::CorsLayer::permissive())
-            .with_state(app_state.clone());
+    if let Some(addr) = settings.ws {
+        // 4. HTTP Listener (WebSocket clients and REST API)
+        let app = http::router(HttpState {
+            rooms: rooms.clone(),
+            default_room,
+            default_config: config,
+        })
+        .layer(tower_http::cors::CorsLayer::permissive());
 
         let listener = tokio::net::TcpListener::bind(&addr).await?;
-        log::info!("Listening (WS) at {addr}");
-
+        let shutdown = rooms.shutdown_token();
+        let served = match tls {
+            None => {
+                log::info!("Listening (WS/HTTP) at {addr}");
+                tokio::spawn(async move {
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(shutdown.cancelled_owned())
+                        .await
+                })
+            }
+            Some(tls) => {
+                log::info!("Listening (WSS/HTTPS) at {addr}");
+                let listener = TlsListener::new(listener, tls)?;
+                tokio::spawn(async move {
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(shutdown.cancelled_owned())
+                        .await
+                })
+            }
+        };
         tokio::spawn(async move {
-            if let Err(e) = axum::serve(listener, app).await {
+            if let Ok(Err(e)) = served.await {
                 log::error!("Axum server error: {e}");
             }
         });
     }
 
-    // Wait for shutdown signal
-    shutdown_rx.await.ok();
-    log::trace!("Shutdown signal received");
+    // 5. Engines (Raw protocol over stdin/stdout), seated in the order given
+    for (command, protocol) in &settings.engines {
+        engine::start(command, *protocol, app_state.clone())
+            .await
+            .with_context(|| format!("Failed to start engine {command}"))?;
+    }
+
+    // Wait for the default room to finish, or for a signal asking to shut down
+    tokio::select! {
+        result = server_handle => {
+            if let Err(e) = result {
+                log::error!("Server task failed: {e:?}");
+            }
+        }
+        signal = shutdown_signal() => log::info!("Received {signal}, shutting down"),
+    }
+
+    // Stop accepting connections, end or suspend the games in progress and let the clients drain
+    rooms.shutdown();
+    let drain = settings.shutdown_timeout;
+    if tokio::time::timeout(drain, rooms.wait()).await.is_err() {
+        log::warn!("Connections still open after {drain:?}, exiting anyway");
+    }
+    if let Some(path) = &settings.unix
+        && let Err(e) = std::fs::remove_file(path)
+    {
+        log::warn!("Failed to remove socket {}: {e:?}", path.display());
+    }
+    log::trace!("Shutdown complete");
 
     Ok(())
 }
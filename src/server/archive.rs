@@ -0,0 +1,244 @@
+//! # Archive Module
+//!
+//! This module archives finished games to a directory, as the main data source for bot analysis.
+//!
+//! Every finished game is written to its own file, `<id>.json`, holding a self-contained
+//! [`ArchivedGame`]. A summary of each game is appended to the index file (`index.jsonl`,
+//! one JSON [`IndexEntry`] per line), from which games are listed without reading every record.
+//!
+//! ## Key Components
+//! - [`Archive`]: Writer of the archive directory.
+//! - [`ArchivedGame`]: Record of a finished game.
+//! - [`IndexEntry`]: Summary of a game in the index file.
+//! - [`ArchiveFilter`]: Criteria to select archived games.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    GameResult, Scores, Variant,
+    board::{movement::MovementIndices, player::Player},
+};
+
+use super::{
+    ServerConfig,
+    rooms::{GameSnapshot, Seat},
+};
+
+/// Name of the index file of an archive directory
+pub const INDEX_FILE: &str = "index.jsonl";
+
+/// Record of a finished game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedGame {
+    /// Identifier of the room the game was played in
+    pub id: Uuid,
+    /// Game variant
+    pub variant: Variant,
+    /// Maximum number of turns, if limited
+    pub max_turns: Option<usize>,
+    /// Players of the game along with the names announced by their clients
+    pub seats: Vec<ArchivedSeat>,
+    /// Creation time of the room, in milliseconds since the Unix epoch
+    pub created_at: u64,
+    /// Time the game started, in milliseconds since the Unix epoch, if known
+    pub started_at: Option<u64>,
+    /// Time the game finished, in milliseconds since the Unix epoch
+    pub finished_at: u64,
+    /// Movements played along with the player who made them, in absolute coordinates
+    pub history: Vec<(Player, MovementIndices)>,
+    /// Result of the game
+    pub result: GameResult,
+}
+
+/// Seat of an archived game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSeat {
+    pub player: Player,
+    pub name: Option<String>,
+}
+
+impl From<&Seat> for ArchivedSeat {
+    fn from(seat: &Seat) -> Self {
+        Self {
+            player: seat.player,
+            name: seat.name.clone(),
+        }
+    }
+}
+
+impl ArchivedGame {
+    /// Builds the record of a finished game from the last state published by its room
+    ///
+    /// Returns `None` if the game did not finish with a result.
+    pub fn new(
+        id: Uuid,
+        created_at: u64,
+        config: &ServerConfig,
+        snapshot: &GameSnapshot,
+    ) -> Option<Self> {
+        let result = snapshot.result.clone()?;
+        Some(Self {
+            id,
+            variant: Variant::Classic,
+            max_turns: (config.max_turns != usize::MAX).then_some(config.max_turns),
+            seats: snapshot.seats.iter().map(ArchivedSeat::from).collect(),
+            created_at,
+            started_at: snapshot.started_at,
+            finished_at: snapshot.finished_at.unwrap_or(created_at),
+            history: snapshot.history.clone(),
+            result,
+        })
+    }
+}
+
+/// Summary of an archived game, as stored in the index file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Identifier of the room the game was played in
+    pub id: Uuid,
+    /// Name of the record file within the archive directory
+    pub file: String,
+    /// Time the game finished, in milliseconds since the Unix epoch
+    pub finished_at: u64,
+    /// Players of the game along with the names announced by their clients
+    pub seats: Vec<ArchivedSeat>,
+    /// Type of result (see [`GameResult::kind`])
+    pub result: String,
+    /// Winner of the game, if any
+    pub winner: Option<Player>,
+    /// Number of turns played
+    pub total_turns: usize,
+    /// Final scores
+    pub scores: Scores,
+}
+
+impl From<&ArchivedGame> for IndexEntry {
+    fn from(game: &ArchivedGame) -> Self {
+        Self {
+            id: game.id,
+            file: format!("{}.json", game.id),
+            finished_at: game.finished_at,
+            seats: game.seats.clone(),
+            result: game.result.kind().to_string(),
+            winner: game.result.winner(),
+            total_turns: game.result.total_turns(),
+            scores: game.result.scores(),
+        }
+    }
+}
+
+/// Writer of an archive directory
+///
+/// Shared by every room, writes to the index file are serialized.
+#[derive(Debug)]
+pub struct Archive {
+    /// Archive directory
+    dir: PathBuf,
+    /// Guards appends to the index file
+    index_lock: Mutex<()>,
+}
+
+impl Archive {
+    /// Opens an archive directory, creating it if needed
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create archive directory {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            index_lock: Mutex::new(()),
+        })
+    }
+
+    /// Writes the record of a finished game and adds it to the index
+    pub fn store(&self, game: &ArchivedGame) -> Result<()> {
+        let entry = IndexEntry::from(game);
+
+        // The record is complete on disk before it is indexed
+        let path = self.dir.join(&entry.file);
+        let record = serde_json::to_vec_pretty(game).with_context(|| "Failed to serialize game")?;
+        fs::write(&path, record)
+            .with_context(|| format!("Failed to write archived game {}", path.display()))?;
+
+        let mut line = serde_json::to_vec(&entry).with_context(|| "Failed to serialize entry")?;
+        line.push(b'\n');
+        let _guard = self.index_lock.lock().expect("Archive lock poisoned");
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(INDEX_FILE))
+            .and_then(|mut index| index.write_all(&line))
+            .with_context(|| "Failed to update archive index")
+    }
+}
+
+/// Reads the index of an archive directory
+pub fn read_index(dir: &Path) -> Result<Vec<IndexEntry>> {
+    let path = dir.join(INDEX_FILE);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read archive index {}", path.display()))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("Invalid index entry {}", i + 1))
+        })
+        .collect()
+}
+
+/// Reads the record of an archived game
+pub fn read_game(dir: &Path, entry: &IndexEntry) -> Result<ArchivedGame> {
+    let path = dir.join(&entry.file);
+    let content = fs::read(&path)
+        .with_context(|| format!("Failed to read archived game {}", path.display()))?;
+    serde_json::from_slice(&content).with_context(|| "Invalid archived game")
+}
+
+/// Criteria to select archived games
+///
+/// Unset criteria match every game.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveFilter {
+    /// Name of one of the players
+    pub player: Option<String>,
+    /// Type of result
+    pub result: Option<String>,
+    /// Winner of the game
+    pub winner: Option<Player>,
+    /// Minimum number of turns played
+    pub min_turns: Option<usize>,
+    /// Games finished at or after this time, in milliseconds since the Unix epoch
+    pub since: Option<u64>,
+}
+
+impl ArchiveFilter {
+    /// Checks whether an archived game matches every criteria
+    pub fn matches(&self, entry: &IndexEntry) -> bool {
+        self.player.as_ref().is_none_or(|player| {
+            entry
+                .seats
+                .iter()
+                .any(|seat| seat.name.as_ref() == Some(player))
+        }) && self
+            .result
+            .as_ref()
+            .is_none_or(|result| &entry.result == result)
+            && self
+                .winner
+                .is_none_or(|winner| entry.winner == Some(winner))
+            && self
+                .min_turns
+                .is_none_or(|turns| entry.total_turns >= turns)
+            && self.since.is_none_or(|since| entry.finished_at >= since)
+    }
+}
@@ -0,0 +1,434 @@
+//! # Bot Module
+//!
+//! This module implements the built-in players of the server, which take seats of the default room
+//! or replace absent players.
+//!
+//! A bot runs as an in-process task talking to the `Server` through the same channels as a `Client`:
+//! it receives `ServerMessage`s and `ServerBroadcast`s, and answers with `ClientMessage`s.
+//! It keeps its own copy of the game to evaluate the available movements. Draw offers and
+//! takeback requests are declined, while rematches are accepted.
+//!
+//! ## Key Components
+//! - [`BotKind`]: Strategy of a bot.
+//! - [`BotSeat`]: Bot along with the seat it takes.
+//! - [`start`]: Seats a bot in a room through the usual seat reservation.
+//! - [`spawn`]: Runs a bot in a seat already given to it by the server.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result, anyhow, bail};
+use itertools::Itertools;
+use rand::{Rng, seq::IndexedRandom};
+use rand_xoshiro::{Xoshiro256PlusPlus, rand_core::SeedableRng};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    Game, GameStatus,
+    board::{Board, HexIdx, goal_indices, hex_distance, movement::MovementIndices, player::Player},
+};
+
+use super::{
+    MainThreadMessage,
+    handshake::AppState,
+    messages::{ClientMessage, ClientRequest, ServerBroadcast, ServerMessage},
+};
+
+/// Plies explored by the search bot, its own movement included
+const SEARCH_DEPTH: usize = 2;
+/// Evaluation of a won game, above any evaluation of a game in progress
+const WIN_SCORE: i64 = 1_000_000;
+/// Capacity of the channel from the server to a bot
+const CHANNEL_CAPACITY: usize = 32;
+/// Center of the board
+const CENTER: HexIdx = [8, 8];
+
+/// Strategy of a bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotKind {
+    /// Plays any available movement
+    Random,
+    /// Plays the movement that brings a piece closest to the goal
+    Greedy,
+    /// Plays the movement with the best outcome after the answer of the opponent
+    Search,
+}
+
+impl FromStr for BotKind {
+    type Err = anyhow::Error;
+
+    fn from_str(kind: &str) -> Result<Self> {
+        match kind {
+            "random" => Ok(Self::Random),
+            "greedy" => Ok(Self::Greedy),
+            "search" => Ok(Self::Search),
+            _ => bail!("Unknown bot {kind}, expected random, greedy or search"),
+        }
+    }
+}
+
+impl fmt::Display for BotKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random => write!(f, "random"),
+            Self::Greedy => write!(f, "greedy"),
+            Self::Search => write!(f, "search"),
+        }
+    }
+}
+
+impl BotKind {
+    /// Name announced to the other players
+    pub fn name(&self) -> String {
+        format!("{self} bot")
+    }
+}
+
+/// Bot along with the seat it takes
+///
+/// Parsed from the kind of the bot, optionally followed by the number of its seat (e.g. `greedy:2`).
+/// Bots without a seat take the first free one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct BotSeat {
+    pub kind: BotKind,
+    pub player: Option<Player>,
+}
+
+impl FromStr for BotSeat {
+    type Err = anyhow::Error;
+
+    fn from_str(seat: &str) -> Result<Self> {
+        let (kind, player) = match seat.split_once(':') {
+            None => (seat, None),
+            Some((kind, number)) => {
+                let player = number
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| number.checked_sub(1))
+                    .and_then(|index| Player::variants().get(index).copied())
+                    .ok_or(anyhow!("Invalid seat {number}, expected 1 or 2"))?;
+                (kind, Some(player))
+            }
+        };
+        Ok(Self {
+            kind: kind.parse()?,
+            player,
+        })
+    }
+}
+
+impl TryFrom<String> for BotSeat {
+    type Error = anyhow::Error;
+
+    fn try_from(seat: String) -> Result<Self> {
+        seat.parse()
+    }
+}
+
+/// Seats a bot in a room through the usual seat reservation
+///
+/// Returns the player the bot plays, once it took its seat.
+pub async fn start(seat: BotSeat, app_state: AppState) -> Result<Player> {
+    let AppState {
+        main_tx,
+        client_msg_tx,
+        server_broadcast_tx,
+        tasks,
+    } = app_state;
+
+    let (resp_tx, resp_rx) = oneshot::channel();
+    main_tx
+        .send(MainThreadMessage::ReserveSeat(seat.player, resp_tx))
+        .await
+        .with_context(|| "Failed to contact server")?;
+    let reservation = resp_rx
+        .await
+        .with_context(|| "Server channel error")?
+        .ok_or(match seat.player {
+            Some(player) => anyhow!("Seat {player} is not free"),
+            None => anyhow!("No free seat"),
+        })?;
+
+    let (server_tx, server_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let broadcast_rx = server_broadcast_tx.subscribe();
+    let (joined_tx, joined_rx) = oneshot::channel();
+    main_tx
+        .send(MainThreadMessage::ClientConnected(
+            reservation,
+            Uuid::new_v4(),
+            Some(seat.kind.name()),
+            Some(seat.kind),
+            server_tx,
+            joined_tx,
+        ))
+        .await
+        .with_context(|| "Failed to contact server")?;
+    joined_rx
+        .await
+        .with_context(|| "Server channel error")?
+        .map_err(|reason| anyhow!(reason))?;
+
+    let player = reservation.player;
+    spawn(
+        &tasks,
+        seat.kind,
+        player,
+        server_rx,
+        broadcast_rx,
+        client_msg_tx,
+    );
+    Ok(player)
+}
+
+/// Runs a bot in a seat already given to it by the server, on the given tracker
+pub fn spawn(
+    tasks: &TaskTracker,
+    kind: BotKind,
+    player: Player,
+    server_rx: mpsc::Receiver<ServerMessage>,
+    broadcast_rx: broadcast::Receiver<ServerBroadcast>,
+    client_tx: mpsc::Sender<ClientMessage>,
+) {
+    log::info!("Seating {kind} bot as {player}");
+    let bot = Bot {
+        kind,
+        player,
+        game: Game::new(),
+        rng: Xoshiro256PlusPlus::from_os_rng(),
+        server_rx,
+        broadcast_rx,
+        client_tx,
+    };
+    tasks.spawn(async move {
+        if let Err(e) = bot.run().await {
+            log::error!("Bot {player} stopped: {e:?}");
+        }
+    });
+}
+
+/// In-process player
+struct Bot {
+    /// Strategy of the bot
+    kind: BotKind,
+    /// Player the bot plays
+    player: Player,
+    /// Copy of the game, kept up to date with the messages of the server
+    game: Game,
+    /// Source of randomness, to break ties
+    rng: Xoshiro256PlusPlus,
+    /// Channel for receiving messages from the server
+    server_rx: mpsc::Receiver<ServerMessage>,
+    /// Channel for receiving broadcasts from the server
+    broadcast_rx: broadcast::Receiver<ServerBroadcast>,
+    /// Channel for sending requests to the server
+    client_tx: mpsc::Sender<ClientMessage>,
+}
+
+impl Bot {
+    async fn run(mut self) -> Result<()> {
+        loop {
+            tokio::select! {
+                // Broadcasts are polled first: the server broadcasts a movement
+                // before sending the next turn, and the bot must keep that order
+                biased;
+
+                broadcast = self.broadcast_rx.recv() => match broadcast {
+                    Err(broadcast::error::RecvError::Closed) => break,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        log::error!("Bot {} lagged by {n} messages", self.player);
+                    }
+                    Ok(ServerBroadcast::Disconnect { .. }) => break,
+                    Ok(message) => self.handle_broadcast(message).await?,
+                },
+
+                message = self.server_rx.recv() => match message {
+                    None | Some(ServerMessage::Disconnect { .. }) => break,
+                    Some(message) => self.handle_message(message).await?,
+                },
+            }
+        }
+
+        // The server may already be gone
+        let _ = self.send(ClientRequest::Disconnect).await;
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, message: ServerMessage) -> Result<()> {
+        match message {
+            ServerMessage::GameState { history, .. } => {
+                self.game = Game::new();
+                for (_, movement) in history {
+                    // Movements come from the server, which validated them
+                    unsafe {
+                        self.game.apply_movement_unchecked(&movement);
+                    }
+                }
+            }
+            ServerMessage::Turn { movements } => {
+                let movement_index = self.select_movement(&movements);
+                self.send(ClientRequest::Choice { movement_index }).await?;
+            }
+            ServerMessage::Error { code, message, .. } => {
+                log::warn!("Bot {} got error {code:?}: {message}", self.player);
+            }
+            ServerMessage::GameStarted { .. } | ServerMessage::Disconnect { .. } => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_broadcast(&mut self, message: ServerBroadcast) -> Result<()> {
+        match message {
+            ServerBroadcast::Movement { movement, .. } => {
+                // Movements come from the server, which validated them
+                unsafe {
+                    self.game.apply_movement_unchecked(&movement);
+                }
+            }
+            ServerBroadcast::TakenBack { movements, .. } => {
+                for _ in movements {
+                    self.game.undo_movement();
+                }
+            }
+            ServerBroadcast::DrawOffered { player } if player != self.player => {
+                self.send(ClientRequest::DeclineDraw).await?;
+            }
+            ServerBroadcast::TakebackRequested { player } if player != self.player => {
+                self.send(ClientRequest::DeclineTakeback).await?;
+            }
+            ServerBroadcast::RematchRequested { player } if player != self.player => {
+                self.send(ClientRequest::Rematch).await?;
+            }
+            ServerBroadcast::SeatsSwapped => self.player = self.player.opponent(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Sends a request to the server
+    async fn send(&self, request: ClientRequest) -> Result<()> {
+        self.client_tx
+            .send(ClientMessage {
+                player: self.player,
+                request,
+            })
+            .await
+            .with_context(|| "Server closed")
+    }
+
+    /// Picks one of the available movements, returning its index
+    fn select_movement(&mut self, movements: &[MovementIndices]) -> usize {
+        let in_sync = matches!(
+            self.game.status(),
+            GameStatus::Playing { player, .. } if player == self.player
+        );
+        if !in_sync {
+            log::warn!(
+                "Bot {} lost track of the game, playing at random",
+                self.player
+            );
+        }
+        if !in_sync || self.kind == BotKind::Random || movements.len() < 2 {
+            return self.rng.random_range(0..movements.len().max(1));
+        }
+
+        let scores = movements
+            .iter()
+            .map(|movement| match self.kind {
+                BotKind::Random => 0,
+                BotKind::Greedy => progress(self.player, movement),
+                BotKind::Search => {
+                    // Checked against the status of the game above
+                    unsafe {
+                        self.game.apply_movement_unchecked(movement);
+                    }
+                    let score = -negamax(
+                        &mut self.game,
+                        SEARCH_DEPTH - 1,
+                        -WIN_SCORE - 1,
+                        WIN_SCORE + 1,
+                    );
+                    self.game.undo_movement();
+                    score
+                }
+            })
+            .collect::<Vec<_>>();
+        let best = scores.iter().copied().max().unwrap_or_default();
+        let candidates = (0..movements.len())
+            .filter(|index| scores[*index] == best)
+            .collect::<Vec<_>>();
+        *candidates.choose(&mut self.rng).unwrap_or(&0)
+    }
+}
+
+/// Cell at the tip of the goal of a player, which its pieces head for
+fn goal_tip(player: Player) -> HexIdx {
+    goal_indices(&player)
+        .into_iter()
+        .max_by_key(|idx| hex_distance(*idx, CENTER))
+        .expect("Goal is not empty")
+}
+
+/// Distance left for the pieces of a player to reach the tip of their goal
+fn distance_left(board: &Board<Player>, player: Player) -> i64 {
+    let tip = goal_tip(player);
+    board
+        .iter_player_indices(&player)
+        .map(|idx| hex_distance(idx, tip) as i64)
+        .sum()
+}
+
+/// Distance a movement brings a piece closer to the tip of the goal
+fn progress(player: Player, [from, to]: &MovementIndices) -> i64 {
+    let tip = goal_tip(player);
+    hex_distance(*from, tip) as i64 - hex_distance(*to, tip) as i64
+}
+
+/// Evaluates a game from the side of the player to move
+fn evaluate(game: &Game) -> i64 {
+    match game.status() {
+        GameStatus::Finished { .. } => -WIN_SCORE,
+        GameStatus::Playing { player, .. } => {
+            distance_left(game.board(), player.opponent()) - distance_left(game.board(), player)
+        }
+    }
+}
+
+/// Explores the movements of the game with alpha-beta pruning
+///
+/// Returns the evaluation of the game from the side of the player to move.
+fn negamax(game: &mut Game, depth: usize, mut alpha: i64, beta: i64) -> i64 {
+    let GameStatus::Playing { player, .. } = game.status() else {
+        // The player who made the last movement won
+        return evaluate(game);
+    };
+    if depth == 0 {
+        return evaluate(game);
+    }
+
+    // Exploring the movements making the most progress first prunes more
+    let movements = game
+        .iter_available_moves()
+        .map(|movement| MovementIndices::from(&movement))
+        .unique()
+        .sorted_by_key(|movement| -progress(player, movement))
+        .collect::<Vec<_>>();
+    let mut best = -WIN_SCORE - 1;
+    for movement in movements {
+        // Generated from the current board
+        unsafe {
+            game.apply_movement_unchecked(&movement);
+        }
+        let score = -negamax(game, depth - 1, -beta, -alpha);
+        game.undo_movement();
+        best = best.max(score);
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
@@ -14,11 +14,19 @@ use futures::{Sink, SinkExt, Stream, StreamExt};
 use tokio::sync::{broadcast, mpsc};
 
 use crate::{
-    server::protocol::{RemoteInMessage, RemoteOutMessage},
-    sternhalma::board::{BOARD_LENGTH, HexIdx, movement::MovementIndices, player::Player},
+    server::protocol::{
+        Capability, ErrorCode, Negotiation, Opponent, RemoteInMessage, RemoteOutMessage,
+    },
+    sternhalma::{
+        GameResult, Scores,
+        board::{BOARD_LENGTH, HexIdx, movement::MovementIndices, player::Player},
+    },
 };
 
-use super::messages::{ClientMessage, ClientRequest, ServerBroadcast, ServerMessage};
+use super::{
+    messages::{ClientMessage, ClientRequest, ServerBroadcast, ServerMessage},
+    metrics::metrics,
+};
 
 // Transport abstraction
 /// Value trait object for sending messages to the remote client
@@ -35,6 +43,8 @@ pub type ClientStream =
 pub struct Client {
     /// Player assigned to client
     player: Player,
+    /// Protocol version and capabilities negotiated during the handshake
+    negotiation: Negotiation,
     /// Sink for messages to remote client (TCP Output)
     sink: ClientSink,
     /// Stream of messages from remote client (TCP Input)
@@ -51,6 +61,7 @@ impl Client {
     /// Creates a new Client instance
     pub fn new(
         player: Player,
+        negotiation: Negotiation,
         sink: ClientSink,
         stream: ClientStream,
         server_rx: mpsc::Receiver<ServerMessage>,
@@ -61,6 +72,7 @@ impl Client {
 
         Ok(Self {
             player,
+            negotiation,
             sink,
             stream,
             server_rx,
@@ -94,13 +106,96 @@ impl Client {
         }
     }
 
+    /// Transforms a relative index from the client to an absolute index
+    ///
+    /// The transformation is its own inverse, but indices sent by the client
+    /// have to be checked to lie on the board's bounding square first.
+    fn absolute_idx(&self, idx: HexIdx) -> Option<HexIdx> {
+        idx.iter()
+            .all(|&c| c < BOARD_LENGTH)
+            .then(|| self.relative_idx(idx))
+    }
+
     /// Transforms an absolute movement to a relative movement for the client
     fn relative_movement(&self, movement: MovementIndices) -> MovementIndices {
         movement.map(|idx| self.relative_idx(idx))
     }
 
+    /// Transforms absolute scores to relative scores for the client
+    fn relative_scores(&self, scores: Scores) -> Scores {
+        match self.player {
+            Player::Player1 => scores,
+            Player::Player2 => [scores[1], scores[0]],
+        }
+    }
+
+    /// Transforms an absolute game result to a relative game result for the client
+    fn relative_result(&self, result: GameResult) -> GameResult {
+        match result {
+            GameResult::Finished {
+                winner,
+                total_turns,
+                scores,
+            } => GameResult::Finished {
+                winner: self.relative_player(winner),
+                total_turns,
+                scores: self.relative_scores(scores),
+            },
+            GameResult::MaxTurns {
+                total_turns,
+                scores,
+            } => GameResult::MaxTurns {
+                total_turns,
+                scores: self.relative_scores(scores),
+            },
+            GameResult::Forfeit {
+                winner,
+                total_turns,
+                scores,
+            } => GameResult::Forfeit {
+                winner: self.relative_player(winner),
+                total_turns,
+                scores: self.relative_scores(scores),
+            },
+            GameResult::Resigned {
+                winner,
+                total_turns,
+                scores,
+            } => GameResult::Resigned {
+                winner: self.relative_player(winner),
+                total_turns,
+                scores: self.relative_scores(scores),
+            },
+            GameResult::Draw {
+                total_turns,
+                scores,
+            } => GameResult::Draw {
+                total_turns,
+                scores: self.relative_scores(scores),
+            },
+            GameResult::Aborted {
+                total_turns,
+                scores,
+            } => GameResult::Aborted {
+                total_turns,
+                scores: self.relative_scores(scores),
+            },
+        }
+    }
+
     /// Sends a message to the remote client via the TCP connection
+    ///
+    /// The message is converted for the negotiated protocol version,
+    /// and messages the client does not know about are not sent.
     async fn send_remote_message(&mut self, message: RemoteOutMessage) -> Result<()> {
+        let version = self.negotiation.protocol_version;
+        let Some(message) = message.downgrade(version) else {
+            log::trace!(
+                "[Player {}] Skipping message unknown to protocol version {version}",
+                self.player
+            );
+            return Ok(());
+        };
         log::debug!(
             "[Player {}] Sending remote message: {message:?}",
             self.player
@@ -145,10 +240,107 @@ impl Client {
                 .send_request(ClientRequest::Choice { movement_index })
                 .await
                 .with_context(|| "Unable to forward message to server"),
-            _ => Ok(()), // Handshake handled separately during connection phase
+            // Movements by coordinates are only accepted once the capability was negotiated
+            RemoteInMessage::Move { .. } | RemoteInMessage::MovePath { .. }
+                if !self.negotiation.has(Capability::CoordinateMoves) =>
+            {
+                self.send_remote_message(RemoteOutMessage::Error {
+                    code: ErrorCode::UnexpectedMessage,
+                    message: "Capability coordinate_moves was not negotiated".to_string(),
+                    context: None,
+                })
+                .await
+                .with_context(|| "Unable to reject movement")
+            }
+            // Forward player movement to the server in absolute coordinates
+            RemoteInMessage::Move { from, to } => {
+                match (self.absolute_idx(from), self.absolute_idx(to)) {
+                    (Some(from), Some(to)) => {
+                        self.send_movement(ClientRequest::Move {
+                            movement: [from, to],
+                        })
+                        .await
+                    }
+                    _ => {
+                        self.reject_movement("Position is outside of the board")
+                            .await
+                    }
+                }
+            }
+            RemoteInMessage::MovePath { path } => {
+                match path
+                    .into_iter()
+                    .map(|idx| self.absolute_idx(idx))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(path) => self.send_movement(ClientRequest::MovePath { path }).await,
+                    None => {
+                        self.reject_movement("Position is outside of the board")
+                            .await
+                    }
+                }
+            }
+            // Forward game negotiations to the server
+            RemoteInMessage::Resign => self.send_negotiation(ClientRequest::Resign).await,
+            RemoteInMessage::OfferDraw => self.send_negotiation(ClientRequest::OfferDraw).await,
+            RemoteInMessage::AcceptDraw => self.send_negotiation(ClientRequest::AcceptDraw).await,
+            RemoteInMessage::DeclineDraw => self.send_negotiation(ClientRequest::DeclineDraw).await,
+            RemoteInMessage::RequestTakeback => {
+                self.send_negotiation(ClientRequest::RequestTakeback).await
+            }
+            RemoteInMessage::AcceptTakeback => {
+                self.send_negotiation(ClientRequest::AcceptTakeback).await
+            }
+            RemoteInMessage::DeclineTakeback => {
+                self.send_negotiation(ClientRequest::DeclineTakeback).await
+            }
+            // Forward the choice of the next game to the server
+            RemoteInMessage::Rematch => self.send_negotiation(ClientRequest::Rematch).await,
+            RemoteInMessage::ReturnToLobby => {
+                self.send_negotiation(ClientRequest::ReturnToLobby).await
+            }
+            // Forward chat messages to the server
+            RemoteInMessage::Chat { text } => self
+                .send_request(ClientRequest::Chat { text })
+                .await
+                .with_context(|| "Unable to forward chat message to server"),
+            // Handshake handled separately during connection phase
+            RemoteInMessage::Hello { .. } | RemoteInMessage::Reconnect { .. } => self
+                .send_remote_message(RemoteOutMessage::Error {
+                    code: ErrorCode::UnexpectedMessage,
+                    message: "Session already established".to_string(),
+                    context: None,
+                })
+                .await
+                .with_context(|| "Unable to reject handshake message"),
         }
     }
 
+    /// Forwards a movement in absolute coordinates to the server
+    async fn send_movement(&mut self, request: ClientRequest) -> Result<()> {
+        self.send_request(request)
+            .await
+            .with_context(|| "Unable to forward movement to server")
+    }
+
+    /// Forwards a resignation, offer or answer to an offer to the server
+    async fn send_negotiation(&mut self, request: ClientRequest) -> Result<()> {
+        self.send_request(request)
+            .await
+            .with_context(|| "Unable to forward negotiation to server")
+    }
+
+    /// Rejects a movement that cannot be forwarded to the server
+    async fn reject_movement(&mut self, message: &str) -> Result<()> {
+        self.send_remote_message(RemoteOutMessage::Error {
+            code: ErrorCode::InvalidMovement,
+            message: message.to_string(),
+            context: None,
+        })
+        .await
+        .with_context(|| "Unable to reject movement")
+    }
+
     /// Handles a broadcast message from the server
     ///
     /// These messages are sent to all connected clients (e.g., game updates).
@@ -157,8 +349,8 @@ impl Client {
 
         match message {
             // Server is shutting down or resetting
-            ServerBroadcast::Disconnect => {
-                self.send_remote_message(RemoteOutMessage::Disconnect)
+            ServerBroadcast::Disconnect { reason } => {
+                self.send_remote_message(RemoteOutMessage::Disconnect { reason })
                     .await?;
             }
             // A player made a move, update remote client
@@ -167,52 +359,117 @@ impl Client {
                 movement,
                 scores,
             } => {
-                // Transform scores to match the relative player perspective
-                let scores = match self.player {
-                    Player::Player1 => scores,
-                    Player::Player2 => [scores[1], scores[0]],
-                };
                 self.send_remote_message(RemoteOutMessage::Movement {
                     player: self.relative_player(player),
                     movement: self.relative_movement(movement),
-                    scores,
+                    scores: self.relative_scores(scores),
+                })
+                .await?;
+            }
+            // A player lost connection
+            ServerBroadcast::PlayerDisconnected {
+                player,
+                grace_period,
+            } => {
+                self.send_remote_message(RemoteOutMessage::OpponentDisconnected {
+                    player: self.relative_player(player),
+                    timeout_secs: grace_period.as_secs(),
+                })
+                .await?;
+            }
+            // A disconnected player is back
+            ServerBroadcast::PlayerReconnected { player } => {
+                // The reconnecting client itself is not notified
+                if player != self.player {
+                    self.send_remote_message(RemoteOutMessage::OpponentReconnected {
+                        player: self.relative_player(player),
+                    })
+                    .await?;
+                }
+            }
+            // Draw offers and takeback requests
+            ServerBroadcast::DrawOffered { player } => {
+                self.send_remote_message(RemoteOutMessage::DrawOffered {
+                    player: self.relative_player(player),
+                })
+                .await?;
+            }
+            ServerBroadcast::DrawDeclined { player } => {
+                self.send_remote_message(RemoteOutMessage::DrawDeclined {
+                    player: self.relative_player(player),
+                })
+                .await?;
+            }
+            ServerBroadcast::TakebackRequested { player } => {
+                self.send_remote_message(RemoteOutMessage::TakebackRequested {
+                    player: self.relative_player(player),
+                })
+                .await?;
+            }
+            ServerBroadcast::TakebackDeclined { player } => {
+                self.send_remote_message(RemoteOutMessage::TakebackDeclined {
+                    player: self.relative_player(player),
+                })
+                .await?;
+            }
+            // Movements were undone, update remote client
+            ServerBroadcast::TakenBack {
+                player,
+                movements,
+                scores,
+            } => {
+                let movements = movements
+                    .into_iter()
+                    .map(|m| self.relative_movement(m))
+                    .collect();
+                self.send_remote_message(RemoteOutMessage::TakenBack {
+                    player: self.relative_player(player),
+                    movements,
+                    scores: self.relative_scores(scores),
+                })
+                .await?;
+            }
+            // A player sent a chat message
+            ServerBroadcast::Chat {
+                player,
+                text,
+                timestamp,
+            } => {
+                self.send_remote_message(RemoteOutMessage::Chat {
+                    player: self.relative_player(player),
+                    text,
+                    timestamp,
                 })
                 .await?;
             }
             // Game has ended
             ServerBroadcast::GameFinished { result } => {
-                let result = match result {
-                    crate::sternhalma::GameResult::Finished {
-                        winner,
-                        total_turns,
-                        scores,
-                    } => {
-                        let scores = match self.player {
-                            Player::Player1 => scores,
-                            Player::Player2 => [scores[1], scores[0]],
-                        };
-                        crate::sternhalma::GameResult::Finished {
-                            winner: self.relative_player(winner),
-                            total_turns,
-                            scores,
-                        }
-                    }
-                    crate::sternhalma::GameResult::MaxTurns {
-                        total_turns,
-                        scores,
-                    } => {
-                        let scores = match self.player {
-                            Player::Player1 => scores,
-                            Player::Player2 => [scores[1], scores[0]],
-                        };
-                        crate::sternhalma::GameResult::MaxTurns {
-                            total_turns,
-                            scores,
-                        }
-                    }
-                };
-                self.send_remote_message(RemoteOutMessage::GameFinished { result })
+                self.send_remote_message(RemoteOutMessage::GameFinished {
+                    result: self.relative_result(result),
+                })
+                .await?;
+            }
+            // A player wants a rematch
+            ServerBroadcast::RematchRequested { player } => {
+                self.send_remote_message(RemoteOutMessage::RematchRequested {
+                    player: self.relative_player(player),
+                })
+                .await?;
+            }
+            // The client plays the other seat from now on, the next game announces it
+            ServerBroadcast::SeatsSwapped => {
+                self.player = self.player.opponent();
+                log::debug!("[Player {}] Swapped seats", self.player);
+            }
+            // A player left their seat
+            ServerBroadcast::PlayerLeft { player } => {
+                // The leaving client is disconnected instead
+                if player != self.player {
+                    self.send_remote_message(RemoteOutMessage::OpponentLeft {
+                        player: self.relative_player(player),
+                    })
                     .await?;
+                }
             }
         };
 
@@ -235,6 +492,77 @@ impl Client {
                 self.send_remote_message(RemoteOutMessage::Turn { movements })
                     .await?;
             }
+            // All players are connected
+            ServerMessage::GameStarted {
+                first_player,
+                players,
+                variant,
+                max_turns,
+            } => {
+                let opponents = players
+                    .into_iter()
+                    .filter(|(player, _)| *player != self.player)
+                    .map(|(player, name)| Opponent {
+                        player: self.relative_player(player),
+                        name,
+                    })
+                    .collect();
+                self.send_remote_message(RemoteOutMessage::GameStarted {
+                    you_move_first: first_player == self.player,
+                    opponents,
+                    variant,
+                    max_turns,
+                })
+                .await?;
+            }
+            // Full game state
+            ServerMessage::GameState {
+                board,
+                to_move,
+                turn,
+                scores,
+                history,
+            } => {
+                let board = board
+                    .into_iter()
+                    .map(|(idx, player)| (self.relative_idx(idx), self.relative_player(player)))
+                    .collect();
+                let history = history
+                    .into_iter()
+                    .map(|(player, movement)| {
+                        (
+                            self.relative_player(player),
+                            self.relative_movement(movement),
+                        )
+                    })
+                    .collect();
+                self.send_remote_message(RemoteOutMessage::GameState {
+                    board,
+                    to_move: to_move.map(|player| self.relative_player(player)),
+                    turn,
+                    scores: self.relative_scores(scores),
+                    history,
+                })
+                .await?;
+            }
+            // This player left their seat
+            ServerMessage::Disconnect { reason } => {
+                self.send_remote_message(RemoteOutMessage::Disconnect { reason })
+                    .await?;
+            }
+            // A request from this player was rejected
+            ServerMessage::Error {
+                code,
+                message,
+                context,
+            } => {
+                self.send_remote_message(RemoteOutMessage::Error {
+                    code,
+                    message,
+                    context,
+                })
+                .await?;
+            }
         }
 
         Ok(())
@@ -254,18 +582,9 @@ impl Client {
 
         loop {
             tokio::select! {
-
-                // Incoming message from server (Direct)
-                server_message = self.server_rx.recv() => {
-                    match server_message {
-                        None => bail!("Server message channel closed"),
-                        Some(message) => {
-                            log::debug!("[Player {}] Received server message: {message:?}",self.player);
-                            self.handle_server_message(message).await.with_context(|| "Unable to handle server message")?;
-                        }
-                    }
-
-                }
+                // Broadcasts are polled first: the server broadcasts a movement
+                // before sending the next turn, and the client must keep that order
+                biased;
 
                 // Incoming broadcast from the server (Broadcast)
                 broadcast = self.broadcast_rx.recv() => {
@@ -275,12 +594,38 @@ impl Client {
                         }
                         Err(broadcast::error::RecvError::Lagged(n)) => {
                             log::error!("[Player {}] Server channel lagged by {n} messages", self.player);
+                            metrics().broadcast_lagged.inc();
                         }
                         Ok(message) => {
                             log::debug!("[Player {}] Received server broadcast: {message:?}",self.player);
+                            // The connection is closed once the client has been told why
+                            let disconnect = matches!(message, ServerBroadcast::Disconnect { .. });
                             self.handle_server_broadcast(message).await.with_context(|| "Unable to handle server broadcast")?;
+                            if disconnect {
+                                log::info!("[Player {}] Disconnected by the server", self.player);
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                // Incoming message from server (Direct)
+                server_message = self.server_rx.recv() => {
+                    match server_message {
+                        None => bail!("Server message channel closed"),
+                        Some(message) => {
+                            log::debug!("[Player {}] Received server message: {message:?}",self.player);
+                            // The connection is closed once the client has been told why
+                            let disconnect = matches!(message, ServerMessage::Disconnect { .. });
+                            self.handle_server_message(message).await.with_context(|| "Unable to handle server message")?;
+                            // The seat is already free, it may be taken by another client before a request arrives
+                            if disconnect {
+                                log::info!("[Player {}] Left their seat", self.player);
+                                return Ok(());
+                            }
                         }
                     }
+
                 }
 
                 // Incoming messages from remote client (Network)
@@ -295,6 +640,14 @@ impl Client {
                         }
                         Some(Err(e)) => {
                             log::error!("[Player {}] Failed to receive remote message: {e:?}",self.player);
+                            let error = RemoteOutMessage::Error {
+                                code: ErrorCode::InvalidMessage,
+                                message: "Unable to decode message".to_string(),
+                                context: Some(format!("{e:#}")),
+                            };
+                            if let Err(e) = self.send_remote_message(error).await {
+                                log::error!("[Player {}] Unable to report invalid message: {e:?}",self.player);
+                            }
                             continue;
                         }
                         None => {
@@ -0,0 +1,166 @@
+//! # Config Module
+//!
+//! This module reads the TOML configuration file of the server.
+//!
+//! Every setting is optional and mirrors a command line argument of the same name:
+//! arguments given on the command line override the values of the file, which override the defaults.
+//! Unknown sections and keys are rejected rather than ignored.
+//!
+//! ```toml
+//! [listeners]
+//! tcp = "0.0.0.0:8080"
+//! ws = "0.0.0.0:8081"
+//! unix = "/run/sternhalma.sock"
+//! unix_mode = 0o660
+//!
+//! [room]
+//! max_turns = 500
+//! persistent = true
+//!
+//! [limits]
+//! timeout = 300
+//! max_rooms = 100
+//!
+//! [bots]
+//! seats = ["greedy:2"]
+//! replace_absent = "search"
+//!
+//! [persistence]
+//! archive_dir = "/var/lib/sternhalma/archive"
+//!
+//! [logging]
+//! level = "info"
+//! ```
+//!
+//! ## Key Components
+//! - [`ConfigFile`]: Settings read from the configuration file, grouped by section.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::{
+    bot::{BotKind, BotSeat},
+    engine::EngineCommand,
+};
+
+/// Settings read from the configuration file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub listeners: ListenersConfig,
+    pub room: RoomConfig,
+    pub limits: LimitsConfig,
+    pub engines: EnginesConfig,
+    pub bots: BotsConfig,
+    pub persistence: PersistenceConfig,
+    pub logging: LoggingConfig,
+}
+
+/// Addresses the server listens on, and the certificate they are served with
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenersConfig {
+    /// Address of the raw TCP listener
+    pub tcp: Option<String>,
+    /// Address of the WebSocket and REST API listener
+    pub ws: Option<String>,
+    /// Address of the human-readable line protocol listener
+    pub telnet: Option<String>,
+    /// Path of the Unix socket
+    pub unix: Option<PathBuf>,
+    /// Permissions of the Unix socket, best written as an octal literal (e.g. `0o660`)
+    pub unix_mode: Option<u32>,
+    /// PEM file holding the TLS certificate chain
+    pub tls_cert: Option<PathBuf>,
+    /// PEM file holding the private key of the TLS certificate
+    pub tls_key: Option<PathBuf>,
+}
+
+/// Settings of the rooms created by the server
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoomConfig {
+    /// Maximum number of turns
+    pub max_turns: Option<usize>,
+    /// Reject chat messages from players
+    pub mute_chat: Option<bool>,
+    /// Keep hosting games once one is over
+    pub persistent: Option<bool>,
+}
+
+/// Limits of the server, times in seconds
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Time to wait for all players to connect
+    pub timeout: Option<u64>,
+    /// Time a disconnected player has to reconnect before forfeiting or losing their seat
+    pub disconnect_timeout: Option<u64>,
+    /// Time to wait for connections to close when shutting down
+    pub shutdown_timeout: Option<u64>,
+    /// Maximum number of rooms hosted at once, beyond which the REST API refuses to create rooms
+    pub max_rooms: Option<usize>,
+}
+
+/// Engines seated in the default room
+///
+/// Engines given on the command line replace those of the file, rather than being added to them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnginesConfig {
+    /// Engines speaking the raw protocol
+    pub raw: Vec<EngineCommand>,
+    /// Engines speaking the text protocol
+    pub text: Vec<EngineCommand>,
+    /// Milliseconds text engines are given to think about each movement
+    pub movetime: Option<u64>,
+}
+
+/// Built-in bots
+///
+/// Bots given on the command line replace those of the file, rather than being added to them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotsConfig {
+    /// Bots seated in the default room, with the syntax of `--bot` (e.g. `greedy:2`)
+    pub seats: Vec<BotSeat>,
+    /// Bot taking the seat of a player who did not reconnect in time
+    pub replace_absent: Option<BotKind>,
+}
+
+/// Directories where games are persisted
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersistenceConfig {
+    /// Directory where games are journaled
+    pub journal_dir: Option<PathBuf>,
+    /// Directory where finished games are archived
+    pub archive_dir: Option<PathBuf>,
+}
+
+/// Settings of the logger
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Log filter, with the syntax of `RUST_LOG` (e.g. `info` or `sternhalma_server=debug`)
+    ///
+    /// `RUST_LOG` takes precedence when it is set.
+    pub level: Option<String>,
+}
+
+impl ConfigFile {
+    /// Reads a configuration file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read configuration file {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Invalid configuration file {}", path.display()))
+    }
+
+    /// Parses the contents of a configuration file
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
@@ -0,0 +1,124 @@
+//! # Engine Module
+//!
+//! This module seats local engine executables as players, talking to them over their standard streams.
+//!
+//! An engine is spawned as a subprocess and talks to the server over its standard streams:
+//! it reads server messages on stdin and writes its own messages on stdout. Its stderr is inherited, for logging.
+//! Engines speak either the raw protocol, framed as on the TCP listener (length-delimited CBOR,
+//! or one JSON object per line), or the line-based text protocol of the [`text_engine`](super::text_engine) module.
+//!
+//! ## Key Components
+//! - [`EngineCommand`]: Command line of an engine.
+//! - [`EngineProtocol`]: Protocol spoken by an engine.
+//! - [`start`]: Spawns an engine and connects it to a room.
+
+use std::{fmt, process::Stdio, str::FromStr, time::Duration};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use tokio::{process::Command, sync::oneshot};
+
+use super::{
+    handshake::{AppState, handle_handshake, raw_connection},
+    metrics::Transport,
+    text_engine,
+};
+
+/// Maximum time an engine has to complete the handshake before the next one is started
+pub const ENGINE_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Protocol spoken by an engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineProtocol {
+    /// Raw protocol, as spoken on the TCP listener
+    Raw,
+    /// Line-based text protocol, with the time given to the engine for each movement
+    Text { movetime: Duration },
+}
+
+/// Command line of an engine
+///
+/// Parsed from a string holding the executable followed by its arguments, separated by whitespace.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct EngineCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl FromStr for EngineCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(command: &str) -> Result<Self> {
+        let mut words = command.split_whitespace().map(String::from);
+        let program = words.next().ok_or(anyhow!("Empty engine command"))?;
+        Ok(Self {
+            program,
+            args: words.collect(),
+        })
+    }
+}
+
+impl TryFrom<String> for EngineCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(command: String) -> Result<Self> {
+        command.parse()
+    }
+}
+
+impl fmt::Display for EngineCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Spawns an engine and connects it to a room
+///
+/// Returns once the engine completed the handshake, so that engines started one after the other
+/// take the seats in order, or after [`ENGINE_HANDSHAKE_TIMEOUT`].
+/// The engine is expected to exit once its connection is closed, and is killed if the server exits first.
+pub async fn start(
+    command: &EngineCommand,
+    protocol: EngineProtocol,
+    app_state: AppState,
+) -> Result<()> {
+    let mut child = Command::new(&command.program)
+        .args(&command.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to spawn {}", command.program))?;
+    let stdin = child.stdin.take().context("Engine stdin not captured")?;
+    let stdout = child.stdout.take().context("Engine stdout not captured")?;
+    let pid = child.id().unwrap_or_default();
+    log::info!("Started engine {command} (pid {pid})");
+
+    let (stream, sink) = match protocol {
+        EngineProtocol::Raw => raw_connection(tokio::io::join(stdout, stdin)),
+        EngineProtocol::Text { movetime } => text_engine::connection(stdout, stdin, movetime),
+    };
+    let (ready_tx, ready_rx) = oneshot::channel();
+    tokio::spawn(async move {
+        handle_handshake(stream, sink, app_state, Transport::Stdio).await;
+        let _ = ready_tx.send(());
+        match child.wait().await {
+            Ok(status) => log::info!("Engine (pid {pid}) exited with {status}"),
+            Err(e) => log::error!("Failed to wait for engine (pid {pid}): {e:?}"),
+        }
+    });
+
+    if tokio::time::timeout(ENGINE_HANDSHAKE_TIMEOUT, ready_rx)
+        .await
+        .is_err()
+    {
+        log::warn!("Engine {command} did not complete the handshake in time");
+    }
+    Ok(())
+}
@@ -3,18 +3,23 @@
 //! This module handles the initial connection phase for both TCP and WebSocket clients.
 //! It implements the `handle_handshake` function, which:
 //! 1. Negotiates a session (New or Reconnect).
-//! 2. Contacts the main server thread to request a player slot.
+//! 2. Contacts the main server thread to reserve a player slot, then takes it.
 //! 3. Spawns the `Client` task upon success.
 
 use futures::{SinkExt, StreamExt};
-use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{broadcast, mpsc, oneshot},
+};
+use tokio_util::{codec::Framed, task::TaskTracker};
 use uuid::Uuid;
 
 use super::{
     MainThreadMessage,
     client::{Client, ClientSink, ClientStream},
-    messages::{ClientMessage, ServerBroadcast, ServerMessage},
-    protocol::{RemoteInMessage, RemoteOutMessage},
+    messages::{ClientMessage, ClientRequest, ServerBroadcast, ServerMessage},
+    metrics::{Transport, metrics},
+    protocol::{ErrorCode, RemoteInMessage, RemoteOutMessage, ServerCodec, negotiate},
 };
 
 const LOCAL_CHANNEL_CAPACITY: usize = 32;
@@ -29,20 +34,45 @@ pub struct AppState {
     pub client_msg_tx: mpsc::Sender<ClientMessage>,
     /// Channel for the Server to broadcast messages to all Clients
     pub server_broadcast_tx: broadcast::Sender<ServerBroadcast>,
+    /// Tracker of the Client tasks, waited for on shutdown so that they can say goodbye
+    pub tasks: TaskTracker,
+}
+
+/// Splits a connection speaking the raw protocol into the stream and sink used by the handshake
+///
+/// Messages are framed by [`ServerCodec`], as on the TCP listener.
+pub fn raw_connection<S>(connection: S) -> (ClientStream, ClientSink)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (write, read) = Framed::new(connection, ServerCodec::new()).split();
+    let sink: ClientSink = Box::pin(write.sink_map_err(|e| anyhow::anyhow!(e)));
+    let stream: ClientStream = Box::pin(read.map(|msg| msg.map_err(|e| anyhow::anyhow!(e))));
+    (stream, sink)
 }
 
 /// Handles the initial handshake with a client (both TCP and WebSocket).
 ///
 /// This function:
 /// 1. Waits for a `Hello` (new session) or `Reconnect` message.
-/// 2. Contacts the main Server thread to request a player slot or validate a session.
-/// 3. Sends a welcome message (or rejection) to the client.
-/// 4. If successful, spawns a `Client` task to handle the connection for the duration of the game.
-pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, app_state: AppState) {
+/// 2. Negotiates the protocol version and capabilities.
+/// 3. Contacts the main Server thread to reserve and take a player slot, or validate a session.
+/// 4. Sends a welcome message (or rejection) to the client, once its slot is taken.
+/// 5. If successful, spawns a `Client` task to handle the connection for the duration of the game.
+///
+/// The connection is reported in the metrics of the given transport until it is closed.
+pub async fn handle_handshake(
+    mut stream: ClientStream,
+    mut sink: ClientSink,
+    app_state: AppState,
+    transport: Transport,
+) {
+    let connection = metrics().connection(transport);
     let AppState {
         main_tx,
         client_msg_tx,
         server_broadcast_tx,
+        tasks,
     } = app_state;
 
     // 1. Wait for Hello or Reconnect
@@ -59,72 +89,154 @@ pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, ap
     };
 
     match handshake {
-        RemoteInMessage::Hello => {
-            // New Session - Ask Server for free player
+        RemoteInMessage::Hello {
+            protocol_version,
+            client_name,
+            capabilities,
+        } => {
+            // Negotiate protocol
+            let negotiation = match negotiate(protocol_version, &capabilities) {
+                Ok(negotiation) => negotiation,
+                Err(reason) => {
+                    log::warn!("Rejecting client {client_name:?}: {reason}");
+                    metrics().handshake_rejected("protocol");
+                    let _ = sink.send(RemoteOutMessage::Reject { reason }).await;
+                    return;
+                }
+            };
+            log::debug!(
+                "Client {client_name:?} negotiated protocol version {version} with capabilities {capabilities:?}",
+                version = negotiation.protocol_version,
+                capabilities = negotiation.capabilities
+            );
+
+            // New Session - Ask Server to reserve a seat
             let (resp_tx, resp_rx) = oneshot::channel();
             if let Err(e) = main_tx
-                .send(MainThreadMessage::RequestFreePlayer(resp_tx))
+                .send(MainThreadMessage::ReserveSeat(None, resp_tx))
                 .await
             {
                 log::error!("Failed to contact server: {e}");
                 return;
             }
-
-            match resp_rx.await {
-                Ok(Some(player)) => {
-                    let session_id = Uuid::new_v4();
-                    log::info!("New client assigned: {player} (Session: {session_id})");
-
-                    // Send Welcome
-                    if let Err(e) = sink.send(RemoteOutMessage::Welcome { session_id }).await {
-                        log::error!("Failed to send Welcome: {e}");
-                        return;
-                    }
-
-                    // Server thread -> Client thread
-                    let (server_tx, server_rx) =
-                        mpsc::channel::<ServerMessage>(LOCAL_CHANNEL_CAPACITY);
-
-                    // Create client
-                    match Client::new(
-                        player,
-                        sink,
-                        stream,
-                        server_rx,
-                        server_broadcast_tx.subscribe(),
-                        client_msg_tx,
-                    ) {
-                        Err(e) => log::error!("Failed to create client: {e:?}"),
-                        Ok(mut client) => {
-                            tokio::spawn(async move {
-                                if let Err(e) = client.run().await {
-                                    log::error!("Client task error: {e:?}");
-                                }
-                            });
-                            if let Err(e) = main_tx
-                                .send(MainThreadMessage::ClientConnected(
-                                    player, session_id, server_tx,
-                                ))
-                                .await
-                            {
-                                log::error!("Failed to notify server of connection: {e:?}");
-                            }
-                        }
-                    }
-                }
+            let reservation = match resp_rx.await {
+                Ok(Some(reservation)) => reservation,
                 Ok(None) => {
                     log::warn!("No free players");
+                    metrics().handshake_rejected("server_full");
                     let _ = sink
                         .send(RemoteOutMessage::Reject {
                             reason: "Server full".to_string(),
                         })
                         .await;
+                    return;
+                }
+                Err(e) => {
+                    log::error!("Server channel error: {e}");
+                    return;
+                }
+            };
+            let player = reservation.player;
+            let session_id = Uuid::new_v4();
+
+            // Server thread -> Client thread
+            // Messages sent by the server once the seat is taken wait in the channel until the client runs
+            let (server_tx, server_rx) = mpsc::channel::<ServerMessage>(LOCAL_CHANNEL_CAPACITY);
+            let broadcast_rx = server_broadcast_tx.subscribe();
+
+            // Take the seat before welcoming the client, so that a client whose reservation was lost is rejected
+            let (joined_tx, joined_rx) = oneshot::channel();
+            if let Err(e) = main_tx
+                .send(MainThreadMessage::ClientConnected(
+                    reservation,
+                    session_id,
+                    client_name.clone(),
+                    None,
+                    server_tx,
+                    joined_tx,
+                ))
+                .await
+            {
+                log::error!("Failed to notify server of connection: {e:?}");
+                return;
+            }
+            match joined_rx.await {
+                Ok(Ok(())) => {}
+                Ok(Err(reason)) => {
+                    log::warn!("Rejecting client {client_name:?}: {reason}");
+                    metrics().handshake_rejected("reservation_lost");
+                    let _ = sink.send(RemoteOutMessage::Reject { reason }).await;
+                    return;
+                }
+                Err(e) => {
+                    log::error!("Server channel error: {e}");
+                    return;
+                }
+            }
+            log::info!(
+                "New client {name} assigned: {player} (Session: {session_id})",
+                name = client_name.as_deref().unwrap_or("<unnamed>")
+            );
+
+            // Send Welcome
+            if let Err(e) = sink
+                .send(RemoteOutMessage::Welcome {
+                    session_id,
+                    protocol_version: negotiation.protocol_version,
+                    capabilities: negotiation.capabilities.clone(),
+                })
+                .await
+            {
+                log::error!("Failed to send Welcome: {e}");
+                // The seat is already taken, the server handles it as any disconnection
+                let _ = client_msg_tx
+                    .send(ClientMessage {
+                        player,
+                        request: ClientRequest::Disconnect,
+                    })
+                    .await;
+                return;
+            }
+
+            // Create client
+            match Client::new(
+                player,
+                negotiation,
+                sink,
+                stream,
+                server_rx,
+                broadcast_rx,
+                client_msg_tx,
+            ) {
+                Err(e) => log::error!("Failed to create client: {e:?}"),
+                Ok(mut client) => {
+                    tasks.spawn(async move {
+                        let _connection = connection;
+                        if let Err(e) = client.run().await {
+                            log::error!("Client task error: {e:?}");
+                        }
+                    });
                 }
-                Err(e) => log::error!("Server channel error: {e}"),
             }
         }
-        RemoteInMessage::Reconnect { session_id: uuid } => {
+        RemoteInMessage::Reconnect {
+            session_id: uuid,
+            protocol_version,
+            capabilities,
+        } => {
             log::info!("Reconnection attempt: {uuid}");
+
+            // Negotiate protocol
+            let negotiation = match negotiate(protocol_version, &capabilities) {
+                Ok(negotiation) => negotiation,
+                Err(reason) => {
+                    log::warn!("Rejecting reconnection {uuid}: {reason}");
+                    metrics().handshake_rejected("protocol");
+                    let _ = sink.send(RemoteOutMessage::Reject { reason }).await;
+                    return;
+                }
+            };
+
             let (resp_tx, resp_rx) = oneshot::channel();
             if let Err(e) = main_tx
                 .send(MainThreadMessage::ClientReconnectedHandle(uuid, resp_tx))
@@ -138,7 +250,11 @@ pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, ap
                 Ok(Some(player)) => {
                     // Ack
                     if let Err(e) = sink
-                        .send(RemoteOutMessage::Welcome { session_id: uuid })
+                        .send(RemoteOutMessage::Welcome {
+                            session_id: uuid,
+                            protocol_version: negotiation.protocol_version,
+                            capabilities: negotiation.capabilities.clone(),
+                        })
                         .await
                     {
                         log::error!("Failed to send Welcome: {e}");
@@ -149,6 +265,7 @@ pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, ap
                         mpsc::channel::<ServerMessage>(LOCAL_CHANNEL_CAPACITY);
                     match Client::new(
                         player,
+                        negotiation,
                         sink,
                         stream,
                         server_rx,
@@ -157,7 +274,8 @@ pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, ap
                     ) {
                         Err(e) => log::error!("Failed to create client: {e:?}"),
                         Ok(mut client) => {
-                            tokio::spawn(async move {
+                            tasks.spawn(async move {
+                                let _connection = connection;
                                 if let Err(e) = client.run().await {
                                     log::error!("Client task error: {e:?}");
                                 }
@@ -170,6 +288,7 @@ pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, ap
                 }
                 Ok(None) => {
                     log::warn!("Unknown session: {uuid}");
+                    metrics().handshake_rejected("unknown_session");
                     let _ = sink
                         .send(RemoteOutMessage::Reject {
                             reason: "Unknown Session".to_string(),
@@ -181,6 +300,14 @@ pub async fn handle_handshake(mut stream: ClientStream, mut sink: ClientSink, ap
         }
         _ => {
             log::error!("Invalid handshake message");
+            metrics().handshake_rejected("unexpected_message");
+            let _ = sink
+                .send(RemoteOutMessage::Error {
+                    code: ErrorCode::UnexpectedMessage,
+                    message: "Expected hello or reconnect".to_string(),
+                    context: None,
+                })
+                .await;
         }
     };
 }
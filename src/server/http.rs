@@ -0,0 +1,296 @@
+//! # HTTP Module
+//!
+//! This module provides the axum router of the server: the WebSocket endpoints, a REST API
+//! to query and create game rooms without speaking the game protocol, and a live feed of games.
+//!
+//! ## Endpoints
+//! - `GET /ws`: WebSocket connection to the default room.
+//! - `GET /games`: Summary of every room.
+//! - `POST /games`: Create a room.
+//! - `GET /games/{id}`: Full state of a room's game.
+//! - `GET /games/{id}/record`: Record of a room's game.
+//! - `GET /games/{id}/events`: Live feed of a room's game, as Server-Sent Events.
+//! - `POST /games/{id}/chat`: Chat message from a spectator, streamed on the live feed only.
+//! - `GET /metrics`: Metrics of the server, in the Prometheus text format.
+//! - `GET /games/{id}/ws`: WebSocket connection to a room.
+
+use std::time::Duration;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    GameResult, Scores, Variant,
+    board::{movement::MovementIndices, player::Player},
+};
+
+use super::{
+    ServerConfig,
+    bot::BotKind,
+    metrics::metrics,
+    rooms::{GameSnapshot, Room, RoomStatus, Rooms, RoomsError, Seat, SpectatorChatError},
+    sse::events_handler,
+    ws::{room_ws_handler, ws_handler},
+};
+
+/// Shared state of the HTTP handlers
+#[derive(Clone)]
+pub struct HttpState {
+    /// Rooms hosted by the server
+    pub rooms: Rooms,
+    /// Room joined by connections that do not name one
+    pub default_room: Uuid,
+    /// Settings of rooms created without explicit ones
+    pub default_config: ServerConfig,
+}
+
+/// Builds the router serving the WebSocket endpoints and the REST API
+pub fn router(state: HttpState) -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/games", get(list_games).post(create_game))
+        .route("/games/{id}", get(get_game))
+        .route("/games/{id}/record", get(get_record))
+        .route("/games/{id}/events", get(events_handler))
+        .route("/games/{id}/chat", post(spectator_chat))
+        .route("/games/{id}/ws", get(room_ws_handler))
+        .route("/metrics", get(get_metrics))
+        .with_state(state)
+}
+
+/// Error reported by the REST API
+#[derive(Debug, Serialize)]
+struct ApiError {
+    error: String,
+}
+
+/// Response for unknown rooms
+pub(crate) fn room_not_found(id: Uuid) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError {
+            error: format!("Game {id} not found"),
+        }),
+    )
+        .into_response()
+}
+
+/// Summary of a room, as listed by `GET /games`
+#[derive(Debug, Serialize)]
+struct GameSummary {
+    id: Uuid,
+    created_at: u64,
+    status: RoomStatus,
+    seats: Vec<Seat>,
+    turn: usize,
+    scores: Scores,
+    result: Option<GameResult>,
+}
+
+impl GameSummary {
+    fn new(id: Uuid, room: &Room) -> Self {
+        let snapshot = room.snapshot.borrow();
+        Self {
+            id,
+            created_at: room.created_at,
+            status: snapshot.status,
+            seats: snapshot.seats.clone(),
+            turn: snapshot.turn,
+            scores: snapshot.scores,
+            result: snapshot.result.clone(),
+        }
+    }
+}
+
+/// Full state of a room, as returned by `GET /games/{id}`
+#[derive(Debug, Serialize)]
+struct GameDetails {
+    id: Uuid,
+    created_at: u64,
+    #[serde(flatten)]
+    snapshot: GameSnapshot,
+}
+
+/// Record of a game, as exported by `GET /games/{id}/record`
+#[derive(Debug, Serialize)]
+struct GameRecord {
+    id: Uuid,
+    created_at: u64,
+    variant: Variant,
+    max_turns: Option<usize>,
+    seats: Vec<Seat>,
+    movements: Vec<(Player, MovementIndices)>,
+    result: Option<GameResult>,
+}
+
+/// Settings of a room created by `POST /games`
+///
+/// Missing settings are taken from the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CreateGame {
+    max_turns: Option<usize>,
+    timeout: Option<u64>,
+    disconnect_timeout: Option<u64>,
+    mute_chat: Option<bool>,
+    replace_absent: Option<BotKind>,
+    persistent: Option<bool>,
+}
+
+/// Chat message sent by `POST /games/{id}/chat`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpectatorChatMessage {
+    text: String,
+}
+
+/// Response of `POST /games`
+#[derive(Debug, Serialize)]
+struct GameCreated {
+    id: Uuid,
+    /// Path of the WebSocket endpoint to join the room
+    ws: String,
+}
+
+/// `GET /games`
+async fn list_games(State(state): State<HttpState>) -> Json<Vec<GameSummary>> {
+    Json(
+        state
+            .rooms
+            .list()
+            .iter()
+            .map(|(id, room)| GameSummary::new(*id, room))
+            .collect(),
+    )
+}
+
+/// `GET /games/{id}`
+async fn get_game(State(state): State<HttpState>, Path(id): Path<Uuid>) -> Response {
+    let Some(room) = state.rooms.get(&id) else {
+        return room_not_found(id);
+    };
+    let snapshot = room.snapshot.borrow().clone();
+    Json(GameDetails {
+        id,
+        created_at: room.created_at,
+        snapshot,
+    })
+    .into_response()
+}
+
+/// `GET /games/{id}/record`
+async fn get_record(State(state): State<HttpState>, Path(id): Path<Uuid>) -> Response {
+    let Some(room) = state.rooms.get(&id) else {
+        return room_not_found(id);
+    };
+    let snapshot = room.snapshot.borrow().clone();
+    let max_turns = room.config.max_turns;
+    Json(GameRecord {
+        id,
+        created_at: room.created_at,
+        variant: Variant::Classic,
+        max_turns: (max_turns != usize::MAX).then_some(max_turns),
+        seats: snapshot.seats,
+        movements: snapshot.history,
+        result: snapshot.result,
+    })
+    .into_response()
+}
+
+/// `POST /games`
+async fn create_game(State(state): State<HttpState>, body: Option<Json<CreateGame>>) -> Response {
+    let Json(settings) = body.unwrap_or_default();
+    let defaults = state.default_config;
+    let config = ServerConfig {
+        connection_timeout: settings
+            .timeout
+            .map_or(defaults.connection_timeout, Duration::from_secs),
+        max_turns: settings.max_turns.unwrap_or(defaults.max_turns),
+        disconnect_timeout: settings
+            .disconnect_timeout
+            .map_or(defaults.disconnect_timeout, Duration::from_secs),
+        mute_chat: settings.mute_chat.unwrap_or(defaults.mute_chat),
+        replace_absent: settings.replace_absent.or(defaults.replace_absent),
+        persistent: settings.persistent.unwrap_or(defaults.persistent),
+    };
+
+    match state.rooms.create(config) {
+        Ok((id, _)) => (
+            StatusCode::CREATED,
+            Json(GameCreated {
+                id,
+                ws: format!("/games/{id}/ws"),
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            let (status, error) = match e.downcast_ref::<RoomsError>() {
+                Some(RoomsError::Full(_)) => {
+                    log::warn!("Refused to create room: {e}");
+                    (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "Too many games, try again later",
+                    )
+                }
+                Some(RoomsError::ShutDown) => (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "The server is shutting down",
+                ),
+                None => {
+                    log::error!("Failed to create room: {e:?}");
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create game")
+                }
+            };
+            (
+                status,
+                Json(ApiError {
+                    error: error.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// `POST /games/{id}/chat`
+async fn spectator_chat(
+    State(state): State<HttpState>,
+    Path(id): Path<Uuid>,
+    Json(message): Json<SpectatorChatMessage>,
+) -> Response {
+    let Some(room) = state.rooms.get(&id) else {
+        return room_not_found(id);
+    };
+    let (status, error) = match room.spectator_chat.send(message.text) {
+        Ok(message) => return (StatusCode::CREATED, Json(message)).into_response(),
+        Err(SpectatorChatError::Muted) => (StatusCode::FORBIDDEN, "Chat is muted in this room"),
+        Err(SpectatorChatError::Invalid) => {
+            (StatusCode::BAD_REQUEST, "Chat message is empty or too long")
+        }
+        Err(SpectatorChatError::RateLimited) => {
+            (StatusCode::TOO_MANY_REQUESTS, "Too many chat messages")
+        }
+    };
+    (
+        status,
+        Json(ApiError {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// `GET /metrics`
+async fn get_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+}
@@ -0,0 +1,377 @@
+//! # Journal Module
+//!
+//! This module keeps an append-only journal of every game on disk, from which unfinished games
+//! are recovered when the server starts.
+//!
+//! A journal is a file of newline-delimited JSON records named after the room identifier.
+//! It starts with a header describing the room, followed by the sessions of the players
+//! and the movements of the game, and ends with the outcome of the game once it is over.
+//! Every record is synced to disk before the event it describes is announced to the players.
+//! Journals are deleted once their room has nothing left to recover.
+//!
+//! ## Key Components
+//! - [`Journal`]: Writer of the journal of a single game.
+//! - [`JournalRecord`]: Record of a journal.
+//! - [`recover`]: Reads the journals of a directory to restore the unfinished games.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    Game, GameResult, GameStatus,
+    board::{movement::MovementIndices, player::Player},
+};
+
+use super::{ServerConfig, bot::BotKind};
+
+/// Version of the journal format
+pub const JOURNAL_VERSION: u32 = 1;
+
+/// Extension of journal files
+const JOURNAL_EXTENSION: &str = "ndjson";
+
+/// Record of a journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum JournalRecord {
+    /// First record of every journal
+    Header {
+        /// Version of the journal format
+        version: u32,
+        /// Identifier of the room
+        id: Uuid,
+        /// Creation time of the room, in milliseconds since the Unix epoch
+        created_at: u64,
+        /// Whether the room is joined by connections that do not name one
+        default: bool,
+        /// Maximum number of turns, if limited
+        max_turns: Option<usize>,
+        /// Seconds to wait for all players to connect
+        connection_timeout: u64,
+        /// Seconds a disconnected player has to reconnect
+        disconnect_timeout: u64,
+        /// Whether chat messages are rejected
+        mute_chat: bool,
+        /// Bot replacing players who do not reconnect in time
+        #[serde(default)]
+        replace_absent: Option<BotKind>,
+        /// Whether the room keeps hosting games after the first one
+        #[serde(default)]
+        persistent: bool,
+    },
+    /// Player joined the game
+    Session {
+        player: Player,
+        session_id: Uuid,
+        name: Option<String>,
+        /// Kind of the bot taking the seat, if the player is a bot
+        #[serde(default)]
+        bot: Option<BotKind>,
+    },
+    /// Player left their seat, either before the game started or to a bot replacing them
+    Left { player: Player },
+    /// Player made a movement
+    Movement {
+        player: Player,
+        movement: MovementIndices,
+    },
+    /// Last movements were taken back
+    TakenBack { count: usize },
+    /// Game finished with a result
+    Finished { result: GameResult },
+    /// Room closed before the game finished
+    Closed,
+    /// Next game of a persistent room started, on a fresh board
+    NewGame {
+        /// Identifier of the new game
+        id: Uuid,
+        /// Whether the players swapped seats for a rematch
+        swapped: bool,
+    },
+}
+
+impl JournalRecord {
+    /// Header of the journal of a room
+    pub fn header(id: Uuid, created_at: u64, default: bool, config: &ServerConfig) -> Self {
+        JournalRecord::Header {
+            version: JOURNAL_VERSION,
+            id,
+            created_at,
+            default,
+            max_turns: (config.max_turns != usize::MAX).then_some(config.max_turns),
+            connection_timeout: config.connection_timeout.as_secs(),
+            disconnect_timeout: config.disconnect_timeout.as_secs(),
+            mute_chat: config.mute_chat,
+            replace_absent: config.replace_absent,
+            persistent: config.persistent,
+        }
+    }
+}
+
+/// Writer of the journal of a single game
+#[derive(Debug)]
+pub struct Journal {
+    /// Path of the journal file
+    path: PathBuf,
+    /// Journal file, opened for appending
+    file: File,
+}
+
+impl Journal {
+    /// Path of the journal of a room within a directory
+    pub fn path(dir: &Path, id: Uuid) -> PathBuf {
+        dir.join(format!("{id}.{JOURNAL_EXTENSION}"))
+    }
+
+    /// Creates the journal of a new room, starting with its header
+    pub fn create(dir: &Path, header: JournalRecord) -> Result<Self> {
+        let JournalRecord::Header { id, .. } = header else {
+            bail!("Journal must start with a header");
+        };
+        let path = Self::path(dir, id);
+        let file = OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create journal {}", path.display()))?;
+        let mut journal = Self { path, file };
+        journal.append(&header)?;
+        Ok(journal)
+    }
+
+    /// Appends a record to the journal and syncs it to disk
+    pub fn append(&mut self, record: &JournalRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record).with_context(|| "Failed to serialize record")?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("Failed to write journal {}", self.path.display()))
+    }
+
+    /// Deletes the journal once the game it records needs no recovery
+    pub fn remove(self) -> Result<()> {
+        let Self { path, file } = self;
+        drop(file);
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove journal {}", path.display()))
+    }
+}
+
+/// Session of a player restored from its journal
+#[derive(Debug)]
+pub struct RecoveredSession {
+    pub session_id: Uuid,
+    pub player: Player,
+    /// Name announced by the client in its `Hello`
+    pub name: Option<String>,
+    /// Kind of the bot taking the seat, if the player is a bot
+    pub bot: Option<BotKind>,
+}
+
+/// Game restored from its journal
+#[derive(Debug)]
+pub struct RecoveredGame {
+    /// Identifier of the room
+    pub id: Uuid,
+    /// Identifier of the game, which differs from the room one after a persistent room moved on
+    pub game_id: Uuid,
+    /// Creation time of the room, in milliseconds since the Unix epoch
+    pub created_at: u64,
+    /// Whether the room is joined by connections that do not name one
+    pub default: bool,
+    /// Configuration of the room
+    pub config: ServerConfig,
+    /// Sessions of the players
+    pub sessions: Vec<RecoveredSession>,
+    /// Game with every journaled movement applied
+    pub game: Game,
+    /// Journal of the game, to keep appending to
+    pub journal: Journal,
+}
+
+/// Reads the journals of a directory and restores the unfinished games
+///
+/// Journals of games that finished, were closed or had not started yet are deleted,
+/// in case the server stopped before deleting them itself.
+/// Persistent rooms are restored with their latest game.
+/// Journals that cannot be read are reported and skipped.
+pub fn recover(dir: &Path) -> Result<Vec<RecoveredGame>> {
+    let mut games = Vec::new();
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read journal directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry
+            .with_context(|| "Failed to read journal directory entry")?
+            .path();
+        if path.extension().is_none_or(|ext| ext != JOURNAL_EXTENSION) {
+            continue;
+        }
+        match recover_game(&path) {
+            Ok(Some(game)) => {
+                log::info!(
+                    "Recovered game {id} at turn {turn}",
+                    id = game.id,
+                    turn = game.game.status().turns()
+                );
+                games.push(game);
+            }
+            Ok(None) => {
+                log::debug!(
+                    "Removing journal {} with nothing to recover",
+                    path.display()
+                );
+                if let Err(e) = fs::remove_file(&path) {
+                    log::error!("Failed to remove journal {}: {e:?}", path.display());
+                }
+            }
+            Err(e) => log::error!("Failed to recover journal {}: {e:?}", path.display()),
+        }
+    }
+    games.sort_by_key(|game| game.created_at);
+    Ok(games)
+}
+
+/// Restores the game of a single journal, if it is unfinished
+fn recover_game(path: &Path) -> Result<Option<RecoveredGame>> {
+    let content = fs::read(path).with_context(|| "Failed to read journal")?;
+
+    // Parse complete records, a crash may have cut the last one short
+    let mut records = Vec::new();
+    let mut valid_len = 0;
+    for line in content.split_inclusive(|byte| *byte == b'\n') {
+        if !line.ends_with(b"\n") {
+            log::warn!(
+                "Discarding incomplete record at the end of {}",
+                path.display()
+            );
+            break;
+        }
+        records.push(
+            serde_json::from_slice::<JournalRecord>(line)
+                .with_context(|| format!("Invalid record {}", records.len() + 1))?,
+        );
+        valid_len += line.len();
+    }
+
+    let mut records = records.into_iter();
+    let Some(JournalRecord::Header {
+        version,
+        id,
+        created_at,
+        default,
+        max_turns,
+        connection_timeout,
+        disconnect_timeout,
+        mute_chat,
+        replace_absent,
+        persistent,
+    }) = records.next()
+    else {
+        bail!("Journal does not start with a header");
+    };
+    if version != JOURNAL_VERSION {
+        bail!("Unsupported journal version {version}");
+    }
+
+    let mut sessions = Vec::new();
+    let mut game = Game::new();
+    let mut game_id = id;
+    let mut finished = false;
+    for record in records {
+        match record {
+            JournalRecord::Header { .. } => bail!("Unexpected header"),
+            JournalRecord::Session {
+                player,
+                session_id,
+                name,
+                bot,
+            } => sessions.push(RecoveredSession {
+                session_id,
+                player,
+                name,
+                bot,
+            }),
+            JournalRecord::Left { player } => sessions.retain(|session| session.player != player),
+            JournalRecord::Movement { player, movement } => {
+                let [from, to] = movement;
+                let board = game.board();
+                let to_move = matches!(
+                    game.status(),
+                    GameStatus::Playing { player: current, .. } if current == player
+                );
+                if !to_move
+                    || board.get(&from).ok() != Some(&Some(player))
+                    || board.get(&to).ok() != Some(&None)
+                {
+                    bail!("Invalid movement {movement:?} of player {player}");
+                }
+                // Checked against the board above
+                unsafe {
+                    game.apply_movement_unchecked(&movement);
+                }
+            }
+            JournalRecord::TakenBack { count } => {
+                for _ in 0..count {
+                    game.undo_movement()
+                        .ok_or(anyhow!("No movement to take back"))?;
+                }
+            }
+            // Persistent rooms may go on with another game
+            JournalRecord::Finished { .. } => finished = true,
+            JournalRecord::Closed => return Ok(None),
+            JournalRecord::NewGame { id, swapped } => {
+                game_id = id;
+                game = Game::new();
+                finished = false;
+                if swapped {
+                    for session in &mut sessions {
+                        session.player = session.player.opponent();
+                    }
+                }
+            }
+        }
+    }
+
+    // Games that had not started have no players to wait for
+    if finished || sessions.len() < Player::count() {
+        return Ok(None);
+    }
+
+    // Drop the incomplete record before appending new ones
+    let file = OpenOptions::new()
+        .append(true)
+        .open(path)
+        .with_context(|| "Failed to open journal")?;
+    file.set_len(valid_len as u64)
+        .with_context(|| "Failed to truncate journal")?;
+
+    Ok(Some(RecoveredGame {
+        id,
+        game_id,
+        created_at,
+        default,
+        config: ServerConfig {
+            connection_timeout: Duration::from_secs(connection_timeout),
+            max_turns: max_turns.unwrap_or(usize::MAX),
+            disconnect_timeout: Duration::from_secs(disconnect_timeout),
+            mute_chat,
+            replace_absent,
+            persistent,
+        },
+        sessions,
+        game,
+        journal: Journal {
+            path: path.to_path_buf(),
+            file,
+        },
+    }))
+}
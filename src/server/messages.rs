@@ -7,9 +7,14 @@
 //! - [`ServerBroadcast`]: Messages broadcast from Server to all Clients.
 //! - [`ClientMessage`]: Requests from a Client to the Server.
 
-use crate::sternhalma::{
-    GameResult, Scores,
-    board::{movement::MovementIndices, player::Player},
+use std::time::Duration;
+
+use crate::{
+    server::protocol::ErrorCode,
+    sternhalma::{
+        GameResult, Scores, Variant,
+        board::{HexIdx, movement::MovementIndices, player::Player},
+    },
 };
 
 /// Message from the Server Thread to a specific Local Client Thread
@@ -27,6 +32,53 @@ pub enum ServerMessage {
         /// Contains all valid moves the player can make from the current board state.
         movements: Vec<MovementIndices>,
     },
+    /// Game has started
+    ///
+    /// Sent to every player once all players have connected,
+    /// and again after a player reconnects mid-game.
+    GameStarted {
+        /// Player who made the first move
+        first_player: Player,
+        /// Every player in the game along with their client name
+        players: Vec<(Player, Option<String>)>,
+        /// Game variant
+        variant: Variant,
+        /// Maximum number of turns, if limited
+        max_turns: Option<usize>,
+    },
+    /// Full game state
+    ///
+    /// Sent after a player joins or reconnects so that it can rebuild its view of the game.
+    GameState {
+        /// Pieces on the board
+        board: Vec<(HexIdx, Player)>,
+        /// Player to move, if the game is still ongoing
+        to_move: Option<Player>,
+        /// Number of turns played
+        turn: usize,
+        /// Current scores
+        scores: Scores,
+        /// Movements played so far along with the player who made them
+        history: Vec<(Player, MovementIndices)>,
+    },
+    /// Disconnection of this client only
+    ///
+    /// Sent to a player who left their seat. The client closes its connection once the message is delivered.
+    Disconnect {
+        /// Why the client is disconnected
+        reason: Option<String>,
+    },
+    /// Request rejected
+    ///
+    /// Sent when the server ignores a request from the player.
+    Error {
+        /// Machine readable error code
+        code: ErrorCode,
+        /// Human readable description of the error
+        message: String,
+        /// Optional details about the rejected request
+        context: Option<String>,
+    },
 }
 
 /// Message from the Server Thread to ALL Local Client Threads
@@ -38,7 +90,11 @@ pub enum ServerBroadcast {
     /// Disconnection signal
     ///
     /// Sent when the server is shutting down or wants to force a disconnect for all clients.
-    Disconnect,
+    /// Clients close their connection once the signal is delivered.
+    Disconnect {
+        /// Why the server is disconnecting the clients, if worth telling
+        reason: Option<String>,
+    },
     /// Player made a move
     ///
     /// Broadcasted after a player has successfully performed a valid move.
@@ -51,6 +107,70 @@ pub enum ServerBroadcast {
         /// The updated scores after the move
         scores: Scores,
     },
+    /// Player lost connection mid-game
+    ///
+    /// Broadcasted when a player drops off. The player forfeits
+    /// unless they reconnect within the grace period.
+    PlayerDisconnected {
+        /// The player who disconnected
+        player: Player,
+        /// Time left for the player to reconnect
+        grace_period: Duration,
+    },
+    /// Player resumed their session
+    ///
+    /// Broadcasted when a previously disconnected player reconnects in time.
+    PlayerReconnected {
+        /// The player who reconnected
+        player: Player,
+    },
+    /// Player offered a draw
+    ///
+    /// The offer stands until the opponent answers it or a movement is played.
+    DrawOffered {
+        /// The player who offered the draw
+        player: Player,
+    },
+    /// Player declined a draw offer
+    DrawDeclined {
+        /// The player who declined the draw
+        player: Player,
+    },
+    /// Player asked to take back their last movement
+    ///
+    /// The request stands until the opponent answers it or a movement is played.
+    TakebackRequested {
+        /// The player who requested the takeback
+        player: Player,
+    },
+    /// Player declined a takeback request
+    TakebackDeclined {
+        /// The player who declined the takeback
+        player: Player,
+    },
+    /// Movements were taken back
+    ///
+    /// Broadcasted after a takeback was accepted. The game resumes
+    /// with the turn of the player who requested it.
+    TakenBack {
+        /// The player who requested the takeback
+        player: Player,
+        /// The undone movements, most recent first
+        movements: Vec<MovementIndices>,
+        /// The updated scores after undoing the movements
+        scores: Scores,
+    },
+    /// Chat message
+    ///
+    /// Broadcasted when a player sends a chat message to the room, including to the sender.
+    Chat {
+        /// The player who sent the message
+        player: Player,
+        /// Content of the message
+        text: String,
+        /// Time the server received the message, in milliseconds since the Unix epoch
+        timestamp: u64,
+    },
     /// Game has finished
     ///
     /// Broadcasted when the game reaches a terminal state (win or draw).
@@ -58,6 +178,24 @@ pub enum ServerBroadcast {
         /// The result of the game
         result: GameResult,
     },
+    /// Player asked for a rematch after the game finished
+    ///
+    /// The next game starts once every player asked for it.
+    RematchRequested {
+        /// The player who asked for the rematch
+        player: Player,
+    },
+    /// Players swapped seats for a rematch
+    ///
+    /// Broadcasted before the next game starts. Every client now plays the seat of its opponent.
+    SeatsSwapped,
+    /// Player left their seat after the game finished
+    ///
+    /// The room returns to the lobby, where a new player can take the free seat.
+    PlayerLeft {
+        /// The player who left
+        player: Player,
+    },
 }
 
 /// Message from a Local Client Thread to the Server Thread
@@ -78,6 +216,44 @@ pub enum ClientRequest {
         /// Index of the chosen movement
         movement_index: usize,
     },
+    /// Player submitted a movement by its start and end coordinates
+    ///
+    /// Sent when the player describes a move by its cells instead of its index.
+    /// The coordinates are absolute and must match one of the available movements.
+    Move {
+        /// The submitted movement
+        movement: MovementIndices,
+    },
+    /// Player submitted a movement by its hopping path
+    ///
+    /// The coordinates are absolute and still need to be validated by the server.
+    MovePath {
+        /// Cells visited by the piece, starting with its current position
+        path: Vec<HexIdx>,
+    },
+    /// Player resigned the game
+    Resign,
+    /// Player offered a draw to the opponent
+    OfferDraw,
+    /// Player accepted the draw offered by the opponent
+    AcceptDraw,
+    /// Player declined the draw offered by the opponent
+    DeclineDraw,
+    /// Player asked to take back their last movement
+    RequestTakeback,
+    /// Player accepted the takeback requested by the opponent
+    AcceptTakeback,
+    /// Player declined the takeback requested by the opponent
+    DeclineTakeback,
+    /// Player sent a chat message to the room
+    Chat {
+        /// Content of the message
+        text: String,
+    },
+    /// Player asked for a rematch after the game finished
+    Rematch,
+    /// Player left their seat after the game finished, to return to the lobby
+    ReturnToLobby,
 }
 
 /// Packaged client request with identification
@@ -0,0 +1,173 @@
+//! # Metrics Module
+//!
+//! This module collects the Prometheus metrics of the server, exposed by the HTTP listener on `/metrics`.
+//! Metrics are process wide: every room and connection reports to the same [`Metrics`].
+//!
+//! ## Key Components
+//! - [`metrics`]: Accessor to the metrics of the process.
+//! - [`Transport`]: Transport of a connection, used to label connection metrics.
+//! - [`GaugeGuard`]: Keeps a gauge incremented for as long as it is alive.
+
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder, exponential_buckets,
+};
+
+use crate::sternhalma::GameResult;
+
+/// Transport used by a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// Raw TCP socket
+    Tcp,
+    /// WebSocket
+    WebSocket,
+    /// Unix domain socket
+    Unix,
+    /// Standard streams of an engine subprocess
+    Stdio,
+    /// Human-readable line protocol
+    Telnet,
+}
+
+impl Transport {
+    /// Label of the transport in metrics
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Transport::Tcp => "tcp",
+            Transport::WebSocket => "ws",
+            Transport::Unix => "unix",
+            Transport::Stdio => "stdio",
+            Transport::Telnet => "telnet",
+        }
+    }
+}
+
+/// Metrics of the server
+pub struct Metrics {
+    registry: Registry,
+    /// Connections currently open, by transport
+    pub connections: IntGaugeVec,
+    /// Games currently being played
+    pub active_games: IntGauge,
+    /// Games finished, by type of result
+    pub games_finished: IntCounterVec,
+    /// Time taken by players to play their turn
+    pub turn_duration: Histogram,
+    /// Turns per second measured by the game timer
+    pub turn_rate: Histogram,
+    /// Times a client fell behind the server broadcasts and missed messages
+    pub broadcast_lagged: IntCounter,
+    /// Handshakes rejected, by reason
+    pub handshake_rejects: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("sternhalma".to_string()), None)?;
+
+        let connections = IntGaugeVec::new(
+            Opts::new("connections", "Connections currently open"),
+            &["transport"],
+        )?;
+        let active_games = IntGauge::new("active_games", "Games currently being played")?;
+        let games_finished = IntCounterVec::new(
+            Opts::new("games_finished_total", "Games finished by type of result"),
+            &["result"],
+        )?;
+        let turn_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "turn_duration_seconds",
+                "Time taken by players to play their turn",
+            )
+            .buckets(exponential_buckets(0.001, 4.0, 10)?),
+        )?;
+        let turn_rate = Histogram::with_opts(
+            HistogramOpts::new("turn_rate", "Turns per second measured by the game timer")
+                .buckets(exponential_buckets(1.0, 4.0, 10)?),
+        )?;
+        let broadcast_lagged = IntCounter::new(
+            "broadcast_lagged_total",
+            "Times a client fell behind the server broadcasts",
+        )?;
+        let handshake_rejects = IntCounterVec::new(
+            Opts::new("handshake_rejects_total", "Handshakes rejected by reason"),
+            &["reason"],
+        )?;
+
+        registry.register(Box::new(connections.clone()))?;
+        registry.register(Box::new(active_games.clone()))?;
+        registry.register(Box::new(games_finished.clone()))?;
+        registry.register(Box::new(turn_duration.clone()))?;
+        registry.register(Box::new(turn_rate.clone()))?;
+        registry.register(Box::new(broadcast_lagged.clone()))?;
+        registry.register(Box::new(handshake_rejects.clone()))?;
+
+        Ok(Self {
+            registry,
+            connections,
+            active_games,
+            games_finished,
+            turn_duration,
+            turn_rate,
+            broadcast_lagged,
+            handshake_rejects,
+        })
+    }
+
+    /// Tracks an open connection until the returned guard is dropped
+    pub fn connection(&self, transport: Transport) -> GaugeGuard {
+        GaugeGuard::new(self.connections.with_label_values(&[transport.label()]))
+    }
+
+    /// Tracks a game being played until the returned guard is dropped
+    pub fn active_game(&self) -> GaugeGuard {
+        GaugeGuard::new(self.active_games.clone())
+    }
+
+    /// Counts a finished game
+    pub fn game_finished(&self, result: &GameResult) {
+        self.games_finished
+            .with_label_values(&[result.kind()])
+            .inc();
+    }
+
+    /// Counts a rejected handshake
+    pub fn handshake_rejected(&self, reason: &str) {
+        self.handshake_rejects.with_label_values(&[reason]).inc();
+    }
+
+    /// Renders the metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            log::error!("Failed to encode metrics: {e:?}");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Metrics of the process
+pub fn metrics() -> &'static Metrics {
+    static METRICS: LazyLock<Metrics> =
+        LazyLock::new(|| Metrics::new().expect("Failed to register metrics"));
+    &METRICS
+}
+
+/// Keeps a gauge incremented for as long as it is alive
+pub struct GaugeGuard(IntGauge);
+
+impl GaugeGuard {
+    fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
@@ -12,30 +12,81 @@
 //! - [`Server`]: The central struct managing the game state and player sessions.
 
 use std::{
-    collections::{HashMap, HashSet, hash_map},
-    time::Duration,
+    collections::{HashMap, HashSet, VecDeque, hash_map},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result, anyhow, bail};
 use itertools::Itertools;
-use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::{
+    sync::{broadcast, mpsc, oneshot, watch},
+    time::Instant,
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use uuid::Uuid;
 
 use crate::sternhalma::{
-    Game, GameResult, GameStatus,
-    board::{movement::MovementIndices, player::Player},
+    Game, GameResult, GameStatus, Variant,
+    board::{
+        movement::{Movement, MovementIndices},
+        player::Player,
+    },
     timing::GameTimer,
 };
 
 use humansize::{BINARY, format_size};
 
+pub mod archive;
+pub mod bot;
 pub mod client;
+pub mod config;
+pub mod engine;
 pub mod handshake;
+pub mod http;
+pub mod journal;
 pub mod messages;
+pub mod metrics;
 pub mod protocol;
+pub mod rooms;
+pub mod sse;
+pub mod telnet;
+pub mod text_engine;
+pub mod tls;
 pub mod ws;
 
+use archive::{Archive, ArchivedGame};
+use bot::BotKind;
+use journal::{Journal, JournalRecord, RecoveredSession};
 use messages::{ClientMessage, ClientRequest, ServerBroadcast, ServerMessage};
+use metrics::metrics;
+use protocol::{CHAT_MESSAGE_LENGTH, ErrorCode};
+use rooms::{GameSnapshot, LOCAL_CHANNEL_CAPACITY, RoomStatus, Seat};
+
+/// Maximum number of chat messages a player can send within [`CHAT_RATE_WINDOW`]
+const CHAT_RATE_LIMIT: usize = 5;
+/// Sliding window over which chat messages are rate limited
+const CHAT_RATE_WINDOW: Duration = Duration::from_secs(10);
+/// Reason given to players disconnected by a server shutdown
+const SHUTDOWN_REASON: &str = "Server shutting down";
+/// Reason given to players of a journaled game suspended by a server shutdown
+const SUSPEND_REASON: &str = "Server shutting down, reconnect after the restart to resume the game";
+/// Time a reserved seat is held for a client completing its handshake
+const SEAT_RESERVATION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Reason given to a player leaving their seat to return to the lobby
+const LOBBY_REASON: &str = "Returned to the lobby";
+
+/// Seat held for a client completing its handshake
+///
+/// The seat is only given to another client once the reservation expired,
+/// in which case the client holding the reservation is rejected when it tries to take the seat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reservation {
+    /// Reserved seat
+    pub player: Player,
+    /// Identifier telling the reservations of the same seat apart
+    pub id: Uuid,
+}
 
 /// Main thread message to server thread
 ///
@@ -43,14 +94,81 @@ use messages::{ClientMessage, ClientRequest, ServerBroadcast, ServerMessage};
 /// to handle new connections, reconnections, or player slot requests.
 #[derive(Debug)]
 pub enum MainThreadMessage {
-    /// A new client has successfully completed the handshake
-    ClientConnected(Player, Uuid, mpsc::Sender<ServerMessage>),
+    /// A new client takes its reserved seat
+    ///
+    /// The server answers with the reason for refusing the seat if the reservation is no longer valid.
+    ClientConnected(
+        Reservation,
+        Uuid,
+        Option<String>,
+        Option<BotKind>,
+        mpsc::Sender<ServerMessage>,
+        oneshot::Sender<Result<(), String>>,
+    ),
     /// A client is trying to reconnect with an existing session
     ClientReconnected(Player, mpsc::Sender<ServerMessage>),
     /// Request to check if a session ID is valid and get the associated player
     ClientReconnectedHandle(Uuid, oneshot::Sender<Option<Player>>),
-    /// Request to get an available player slot
-    RequestFreePlayer(oneshot::Sender<Option<Player>>),
+    /// Request to reserve an available player slot for a new client, preferably the given one
+    ReserveSeat(Option<Player>, oneshot::Sender<Option<Reservation>>),
+}
+
+/// Current time, in milliseconds since the Unix epoch
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Server configuration
+///
+/// Settings that control the lifecycle of a game session.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Time to wait for all players to connect
+    pub connection_timeout: Duration,
+    /// Maximum number of turns before the game is called
+    pub max_turns: usize,
+    /// Grace period for a disconnected player to reconnect before forfeiting the game
+    pub disconnect_timeout: Duration,
+    /// Whether chat messages are rejected in this room
+    pub mute_chat: bool,
+    /// Bot taking the seat of a player who did not reconnect in time, instead of them forfeiting
+    pub replace_absent: Option<BotKind>,
+    /// Whether the room keeps hosting games once one is over, rather than closing
+    ///
+    /// Players waiting in the lobby of a persistent room are never timed out,
+    /// but the room may close once its lobby is empty (see [`Server::with_idle_timeout`]).
+    pub persistent: bool,
+}
+
+/// Outcome of a single turn
+#[derive(Debug)]
+enum TurnOutcome {
+    /// A movement was applied to the game
+    Played,
+    /// Movements were taken back and the turn has to start over
+    TakenBack,
+    /// The game ended without a movement being applied (e.g. forfeit)
+    Ended(GameResult),
+}
+
+/// How the next game of a persistent room starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NextGame {
+    /// Every player asked for a rematch, which starts right away with the seats swapped
+    Rematch,
+    /// A player left, the room waits in the lobby for the seats to be taken again
+    Lobby,
+}
+
+/// Offer made by a player that waits for the opponent's answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Offer {
+    /// End the game in a draw
+    Draw,
+    /// Undo the last movement of the player
+    Takeback,
 }
 
 /// The Main Server Logic
@@ -59,85 +177,315 @@ pub enum MainThreadMessage {
 /// It manages client connections, tracks game state, and handles the flow of the game.
 #[derive(Debug)]
 pub struct Server {
+    // Identifier of the game in progress, which is the identifier of the room for its first game
+    game_id: Uuid,
+    // Creation time of the room, in milliseconds since the Unix epoch
+    created_at: u64,
     // Channel to receive messages from the main thread
     main_rx: mpsc::Receiver<MainThreadMessage>,
     // Clients list - Active connections to players
     clients_tx: HashMap<Player, mpsc::Sender<ServerMessage>>,
     // Session management - Maps Session IDs to Players
     sessions: HashMap<Uuid, Player>,
+    // Client names announced by the players
+    names: HashMap<Player, String>,
+    // Seats held for clients completing their handshake, along with the deadline of each reservation
+    reservations: HashMap<Player, (Uuid, Instant)>,
     // Disconnected players with active sessions - Players who dropped off but can reconnect
-    disconnected: HashSet<Player>,
+    // Each one is mapped to the deadline after which they forfeit the game
+    disconnected: HashMap<Player, Instant>,
+    // Pending offer along with the player who made it
+    offer: Option<(Player, Offer)>,
+    // Players who asked for a rematch once the game finished
+    rematch: HashSet<Player>,
+    // Time of the recent chat messages of each player, for rate limiting
+    chat_times: HashMap<Player, VecDeque<Instant>>,
     // Channel for broadcasting messages to all local client threads
     broadcast_tx: broadcast::Sender<ServerBroadcast>,
     // Channel for receiving messages from local client threads
     clients_rx: mpsc::Receiver<ClientMessage>,
+    // Channel given to the bots replacing absent players, for sending messages to the server
+    clients_msg_tx: mpsc::Sender<ClientMessage>,
+    // Server configuration
+    config: ServerConfig,
+    // Game state
+    game: Game,
+    // Phase of the game
+    status: RoomStatus,
+    // Result of the game, once finished
+    result: Option<GameResult>,
+    // Time the game started and finished, in milliseconds since the Unix epoch
+    started_at: Option<u64>,
+    finished_at: Option<u64>,
+    // Channel publishing the state of the game
+    snapshot_tx: watch::Sender<GameSnapshot>,
+    // On-disk journal of the game, if enabled
+    journal: Option<Journal>,
+    // Archive of finished games, if enabled
+    archive: Option<Arc<Archive>>,
+    // Signal to abort the game and disconnect the players
+    shutdown: CancellationToken,
+    // Tracker of the bot tasks, waited for on shutdown
+    tasks: TaskTracker,
+    // Time after which the room closes once nobody holds a seat in its lobby, if any
+    idle_timeout: Option<Duration>,
+    // Bots of a recovered game, seated again once the server runs
+    recovered_bots: Vec<(Player, BotKind)>,
 }
 
 impl Server {
-    /// Creates a new server instance
+    /// Creates a new server instance for the room with the given identifier and creation time
     pub fn new(
+        id: Uuid,
+        created_at: u64,
         main_rx: mpsc::Receiver<MainThreadMessage>,
         clients_rx: mpsc::Receiver<ClientMessage>,
+        clients_msg_tx: mpsc::Sender<ClientMessage>,
         broadcast_tx: broadcast::Sender<ServerBroadcast>,
+        config: ServerConfig,
     ) -> Result<Self> {
-        Ok(Self {
+        let server = Self {
+            game_id: id,
+            created_at,
             main_rx,
             clients_tx: HashMap::new(),
             sessions: HashMap::new(),
-            disconnected: HashSet::new(),
+            names: HashMap::new(),
+            reservations: HashMap::new(),
+            disconnected: HashMap::new(),
+            offer: None,
+            rematch: HashSet::new(),
+            chat_times: HashMap::new(),
             broadcast_tx,
             clients_rx,
-        })
+            clients_msg_tx,
+            config,
+            game: Game::new(),
+            status: RoomStatus::Waiting,
+            result: None,
+            started_at: None,
+            finished_at: None,
+            snapshot_tx: watch::Sender::new(GameSnapshot::default()),
+            journal: None,
+            archive: None,
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            idle_timeout: None,
+            recovered_bots: Vec::new(),
+        };
+        server.publish_snapshot();
+
+        Ok(server)
+    }
+
+    /// Records the game in the given journal
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Archives every game finished in the room
+    pub fn with_archive(mut self, archive: Arc<Archive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Aborts the game and disconnects the players once the given token is cancelled
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Spawns the bots seated in the room on the given tracker
+    pub fn with_tasks(mut self, tasks: TaskTracker) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Closes the room once nobody holds a seat in its lobby for the given time
+    ///
+    /// Unlike the connection timeout, it also applies to the lobby of persistent rooms.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Resumes a game restored from its journal
+    ///
+    /// The game continues where it was left, with every human player disconnected.
+    /// Players have the usual grace period to reconnect with their session before forfeiting.
+    /// Bots take their seats back as soon as the server runs.
+    pub fn with_recovered_game(
+        mut self,
+        game_id: Uuid,
+        game: Game,
+        sessions: Vec<RecoveredSession>,
+    ) -> Self {
+        let deadline = Instant::now() + self.config.disconnect_timeout;
+        for session in sessions {
+            let player = session.player;
+            self.sessions.insert(session.session_id, player);
+            if let Some(name) = session.name {
+                self.names.insert(player, name);
+            }
+            match session.bot {
+                Some(kind) => self.recovered_bots.push((player, kind)),
+                None => {
+                    self.disconnected.insert(player, deadline);
+                }
+            }
+        }
+        self.game_id = game_id;
+        self.game = game;
+        self.status = RoomStatus::Playing;
+        self.publish_snapshot();
+        self
+    }
+
+    /// Appends a record to the journal of the game, if enabled
+    fn journal(&mut self, record: JournalRecord) -> Result<()> {
+        match &mut self.journal {
+            Some(journal) => journal.append(&record),
+            None => Ok(()),
+        }
+    }
+
+    /// Subscribes to the state of the game
+    ///
+    /// The receiver keeps the last published state after the server finishes.
+    pub fn snapshots(&self) -> watch::Receiver<GameSnapshot> {
+        self.snapshot_tx.subscribe()
+    }
+
+    /// Captures the current state of the game
+    fn snapshot(&self) -> GameSnapshot {
+        let status = self.game.status();
+        let seats = Player::variants()
+            .into_iter()
+            .filter(|player| {
+                self.clients_tx.contains_key(player) || self.disconnected.contains_key(player)
+            })
+            .map(|player| Seat {
+                player,
+                name: self.names.get(&player).cloned(),
+                connected: self.clients_tx.contains_key(&player),
+            })
+            .collect();
+        let mut board = Vec::new();
+        for piece in Player::variants() {
+            board.extend(
+                self.game
+                    .board()
+                    .iter_player_indices(&piece)
+                    .map(|idx| (idx, piece)),
+            );
+        }
+        let to_move = match status {
+            GameStatus::Playing { player, .. } => Some(player),
+            GameStatus::Finished { .. } => None,
+        };
+
+        GameSnapshot {
+            game_id: self.game_id,
+            status: self.status,
+            seats,
+            board,
+            to_move,
+            turn: status.turns(),
+            scores: status.scores(),
+            history: self.game.iter_history().collect(),
+            result: self.result.clone(),
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+        }
+    }
+
+    /// Publishes the current state of the game to subscribers
+    fn publish_snapshot(&self) {
+        self.snapshot_tx.send_replace(self.snapshot());
     }
 
     /// Wait for all players to connect
     ///
     /// This function blocks until the required number of players have connected.
     /// It handles incoming connections and assigns player slots (P1, P2, etc.).
+    ///
+    /// A player leaving before the game starts keeps their seat for the grace period,
+    /// during which they can reconnect with their session. The seat is freed afterwards.
+    ///
+    /// Fails once the lobby stayed empty for the idle timeout, if any.
     async fn wait_players_connect(&mut self, n_players: usize) -> Result<()> {
+        let mut idle_deadline = None;
         while self.clients_tx.len() < n_players {
-            // Wait for message from main thread
-            match self
-                .main_rx
-                .recv()
-                .await
-                .ok_or(anyhow!("Channel from main thread to server close"))?
-            {
-                // A client has connected
-                MainThreadMessage::ClientConnected(player, session_id, client_tx) => {
-                    // Check if player is already assigned
-                    if let hash_map::Entry::Vacant(entry) = self.clients_tx.entry(player) {
-                        entry.insert(client_tx);
-                        self.sessions.insert(session_id, player);
-                        log::info!(
-                            "Player {player} connected with session {session_id}. ({n_connected}/{n_players})",
-                            n_connected = self.clients_tx.len()
-                        );
-                    } else {
-                        log::error!("Player {player} is already connected");
-                        // TODO: Inform main thread about wrong assignment of player
-                        continue;
-                    }
+            // The idle timeout starts over every time the lobby becomes empty
+            let idle = self.clients_tx.is_empty() && self.disconnected.is_empty();
+            idle_deadline = self
+                .idle_timeout
+                .filter(|_| idle)
+                .map(|timeout| idle_deadline.unwrap_or_else(|| Instant::now() + timeout));
+
+            // Player who left with the earliest reconnection deadline
+            let expired = self
+                .disconnected
+                .iter()
+                .min_by_key(|(_, deadline)| **deadline)
+                .map(|(player, deadline)| (*player, *deadline));
+            let expired_deadline = expired.map_or_else(Instant::now, |(_, deadline)| deadline);
+
+            tokio::select! {
+                // Player who left did not come back in time
+                _ = tokio::time::sleep_until(expired_deadline), if expired.is_some() => {
+                    let (player, _) = expired.expect("Guarded by select precondition");
+                    self.free_seat(player)?;
                 }
-                MainThreadMessage::ClientReconnected(..) => {
-                    log::warn!("Reconnection attempt during connection phase ignored");
+
+                // Nobody took a seat in time
+                _ = tokio::time::sleep_until(idle_deadline.unwrap_or_else(Instant::now)), if idle_deadline.is_some() => {
+                    bail!(
+                        "The lobby stayed empty for {secs} seconds",
+                        secs = self.idle_timeout.unwrap_or_default().as_secs()
+                    );
                 }
-                MainThreadMessage::ClientReconnectedHandle(uuid, resp_tx) => {
-                    // Check if session exists
-                    let player = self.sessions.get(&uuid).copied();
-                    let _ = resp_tx.send(player);
+
+                // Message from main thread
+                main_msg = self.main_rx.recv() => {
+                    let message = main_msg.ok_or(anyhow!("Channel from main thread to server close"))?;
+                    self.handle_lobby_connection(message, n_players).await?;
                 }
-                MainThreadMessage::RequestFreePlayer(resp_tx) => {
-                    // Find a player not in sessions/clients
-                    // We check which players are connected.
-                    // Connected = in self.clients_tx
-                    // We assume max 2 players.
-                    // This logic assumes we want to fill P1 then P2.
-                    // Or any available.
-                    let free_player = [Player::Player1, Player::Player2]
-                        .into_iter()
-                        .find(|p| !self.clients_tx.contains_key(p));
-                    let _ = resp_tx.send(free_player);
+
+                // Message from client thread
+                client_msg = self.clients_rx.recv() => {
+                    let ClientMessage { player, request } =
+                        client_msg.ok_or(anyhow!("Channel from clients closed"))?;
+                    match request {
+                        ClientRequest::Disconnect => {
+                            if self.clients_tx.remove(&player).is_none() {
+                                log::warn!("Player {player} was already disconnected");
+                                continue;
+                            }
+                            let grace_period = self.config.disconnect_timeout;
+                            log::info!(
+                                "Player {player} left before the game started, holding their seat for {secs} seconds",
+                                secs = grace_period.as_secs()
+                            );
+                            self.disconnected.insert(player, Instant::now() + grace_period);
+                            self.publish_snapshot();
+                            let _ = self
+                                .broadcast_tx
+                                .send(ServerBroadcast::PlayerDisconnected { player, grace_period });
+                        }
+                        ClientRequest::Chat { text } => self.handle_chat(player, text).await?,
+                        request => {
+                            log::warn!("Player {player} sent {request:?} before the game started");
+                            self.send_error(
+                                player,
+                                ErrorCode::UnexpectedMessage,
+                                "The game has not started yet".to_string(),
+                                None,
+                            )
+                            .await?;
+                        }
+                    }
                 }
             }
         }
@@ -145,17 +493,284 @@ impl Server {
             "All {n_connected} players connected",
             n_connected = self.clients_tx.len()
         );
+
+        // Announce the start of the game
+        self.status = RoomStatus::Playing;
+        self.started_at = Some(now_millis());
+        self.publish_snapshot();
+        for player in self.clients_tx.keys() {
+            if let Err(e) = self.send_game_started(*player).await {
+                log::error!("Failed to announce game start to player {player}: {e:?}");
+            }
+        }
+
         Ok(())
     }
 
+    /// Handles a connection, reconnection or seat request before the game starts
+    async fn handle_lobby_connection(
+        &mut self,
+        message: MainThreadMessage,
+        n_players: usize,
+    ) -> Result<()> {
+        match message {
+            // A client takes its reserved seat
+            MainThreadMessage::ClientConnected(
+                reservation,
+                session_id,
+                name,
+                bot,
+                client_tx,
+                resp_tx,
+            ) => {
+                let player = reservation.player;
+                // The seat may have been given to another client once the reservation expired
+                if let Err(reason) = self.take_reservation(reservation) {
+                    log::warn!("Refusing seat {player} to session {session_id}: {reason}");
+                    let _ = resp_tx.send(Err(reason));
+                    return Ok(());
+                }
+                if resp_tx.send(Ok(())).is_err() {
+                    log::warn!("Client of session {session_id} left before taking seat {player}");
+                    return Ok(());
+                }
+                self.clients_tx.insert(player, client_tx);
+                self.sessions.insert(session_id, player);
+                self.journal(JournalRecord::Session {
+                    player,
+                    session_id,
+                    name: name.clone(),
+                    bot,
+                })?;
+                if let Some(name) = name {
+                    self.names.insert(player, name);
+                }
+                log::info!(
+                    "Player {player} connected with session {session_id}. ({n_connected}/{n_players})",
+                    n_connected = self.clients_tx.len()
+                );
+                if let Err(e) = self.send_game_state(player).await {
+                    log::error!("Failed to synchronize player {player}: {e:?}");
+                }
+                self.publish_snapshot();
+            }
+            // A player who left comes back to their seat
+            MainThreadMessage::ClientReconnected(player, client_tx) => {
+                if self.disconnected.remove(&player).is_none() {
+                    log::warn!("Player {player} reconnected but was not marked as disconnected");
+                    return Ok(());
+                }
+                log::info!("Player {player} reconnected before the game started");
+                self.clients_tx.insert(player, client_tx);
+                self.publish_snapshot();
+                let _ = self
+                    .broadcast_tx
+                    .send(ServerBroadcast::PlayerReconnected { player });
+                if let Err(e) = self.send_game_state(player).await {
+                    log::error!("Failed to synchronize player {player}: {e:?}");
+                }
+            }
+            MainThreadMessage::ClientReconnectedHandle(uuid, resp_tx) => {
+                // Check if session exists
+                let player = self.sessions.get(&uuid).copied();
+                let _ = resp_tx.send(player);
+            }
+            MainThreadMessage::ReserveSeat(preferred, resp_tx) => {
+                let reservation = self.reserve_seat(preferred);
+                if resp_tx.send(reservation).is_err()
+                    && let Some(reservation) = reservation
+                {
+                    // Roll back the reservation of a client that is already gone
+                    self.reservations.remove(&reservation.player);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives the seat of an absent player to a bot
+    ///
+    /// The session of the player is forgotten, so that they cannot take their seat back from the bot.
+    fn seat_bot(&mut self, kind: BotKind, player: Player) -> Result<()> {
+        self.disconnected.remove(&player);
+        self.sessions.retain(|_, seated| *seated != player);
+        self.chat_times.remove(&player);
+        self.journal(JournalRecord::Left { player })?;
+
+        // The bot has a session of its own, so that a recovered game replaces it again
+        let session_id = Uuid::new_v4();
+        let name = kind.name();
+        self.sessions.insert(session_id, player);
+        self.journal(JournalRecord::Session {
+            player,
+            session_id,
+            name: Some(name.clone()),
+            bot: Some(kind),
+        })?;
+        self.names.insert(player, name);
+
+        self.spawn_bot(kind, player);
+        self.publish_snapshot();
+        let _ = self
+            .broadcast_tx
+            .send(ServerBroadcast::PlayerReconnected { player });
+        Ok(())
+    }
+
+    /// Spawns a bot playing the given seat
+    fn spawn_bot(&mut self, kind: BotKind, player: Player) {
+        let (server_tx, server_rx) = mpsc::channel(LOCAL_CHANNEL_CAPACITY);
+        self.clients_tx.insert(player, server_tx);
+        bot::spawn(
+            &self.tasks,
+            kind,
+            player,
+            server_rx,
+            self.broadcast_tx.subscribe(),
+            self.clients_msg_tx.clone(),
+        );
+    }
+
+    /// Brings a player joining a game in progress up to date
+    async fn synchronize_player(&mut self, player: Player) {
+        if let Err(e) = self.send_game_state(player).await {
+            log::error!("Failed to synchronize player {player}: {e:?}");
+        }
+        if let Err(e) = self.send_game_started(player).await {
+            log::error!("Failed to announce game to player {player}: {e:?}");
+        }
+    }
+
+    /// Frees the seat of a player who left before the game started
+    ///
+    /// The session of the player is forgotten, so that the seat can be given to a new client.
+    fn free_seat(&mut self, player: Player) -> Result<()> {
+        log::info!("Player {player} did not come back in time, freeing their seat");
+        self.disconnected.remove(&player);
+        self.sessions.retain(|_, seated| *seated != player);
+        self.names.remove(&player);
+        self.chat_times.remove(&player);
+        self.journal(JournalRecord::Left { player })?;
+        self.publish_snapshot();
+        Ok(())
+    }
+
+    /// Reserves the first seat that is neither taken nor held by a pending reservation
+    ///
+    /// Expired reservations are replaced, so that a client that never completes its handshake
+    /// does not hold a seat for longer than [`SEAT_RESERVATION_TIMEOUT`].
+    /// When a seat is preferred, no other seat is reserved.
+    fn reserve_seat(&mut self, preferred: Option<Player>) -> Option<Reservation> {
+        let now = Instant::now();
+        let player = Player::variants().into_iter().find(|player| {
+            preferred.is_none_or(|preferred| preferred == *player)
+                && !self.clients_tx.contains_key(player)
+                && !self.disconnected.contains_key(player)
+                && self
+                    .reservations
+                    .get(player)
+                    .is_none_or(|(_, deadline)| *deadline <= now)
+        })?;
+        let reservation = Reservation {
+            player,
+            id: Uuid::new_v4(),
+        };
+        self.reservations
+            .insert(player, (reservation.id, now + SEAT_RESERVATION_TIMEOUT));
+        log::debug!("Reserved seat {player} ({id})", id = reservation.id);
+        Some(reservation)
+    }
+
+    /// Consumes a reservation so that its client takes the seat
+    ///
+    /// An expired reservation is still honoured as long as the seat was not reserved again in the meantime.
+    /// Returns the reason for refusing the seat otherwise.
+    fn take_reservation(&mut self, reservation: Reservation) -> Result<(), String> {
+        let player = reservation.player;
+        match self.reservations.entry(player) {
+            hash_map::Entry::Occupied(entry) if entry.get().0 == reservation.id => {
+                entry.remove();
+                Ok(())
+            }
+            _ if self.clients_tx.contains_key(&player) => {
+                Err(format!("Seat {player} was given to another client"))
+            }
+            _ => Err(format!("Reservation of seat {player} expired")),
+        }
+    }
+
+    /// Sends the full game state to a connected player
+    async fn send_game_state(&self, player: Player) -> Result<()> {
+        let client_tx = self
+            .clients_tx
+            .get(&player)
+            .ok_or(anyhow!("Unable to find player {player}"))?;
+        let snapshot = self.snapshot();
+        client_tx
+            .send(ServerMessage::GameState {
+                board: snapshot.board,
+                to_move: snapshot.to_move,
+                turn: snapshot.turn,
+                scores: snapshot.scores,
+                history: snapshot.history,
+            })
+            .await
+            .with_context(|| format!("Failed to send game state to player {player}"))
+    }
+
+    /// Informs a connected player about the players and settings of the game
+    async fn send_game_started(&self, player: Player) -> Result<()> {
+        let client_tx = self
+            .clients_tx
+            .get(&player)
+            .ok_or(anyhow!("Unable to find player {player}"))?;
+        let players = Player::variants()
+            .into_iter()
+            .map(|player| (player, self.names.get(&player).cloned()))
+            .collect();
+        let max_turns = self.config.max_turns;
+        client_tx
+            .send(ServerMessage::GameStarted {
+                // Games always start with the first player
+                first_player: Player::Player1,
+                players,
+                variant: Variant::Classic,
+                max_turns: (max_turns != usize::MAX).then_some(max_turns),
+            })
+            .await
+            .with_context(|| format!("Failed to send game start to player {player}"))
+    }
+
+    /// Informs a connected player that one of their requests was rejected
+    async fn send_error(
+        &self,
+        player: Player,
+        code: ErrorCode,
+        message: String,
+        context: Option<String>,
+    ) -> Result<()> {
+        let Some(client_tx) = self.clients_tx.get(&player) else {
+            log::warn!("Unable to inform disconnected player {player} of error {code:?}");
+            return Ok(());
+        };
+        client_tx
+            .send(ServerMessage::Error {
+                code,
+                message,
+                context,
+            })
+            .await
+            .with_context(|| format!("Failed to send error message to player {player}"))
+    }
+
     /// Disconnects all players
     ///
     /// Gracefully shuts down connections by sending a broadcast disconnect signal
     /// and waiting for clients to acknowledge/close.
-    async fn disconnect_players(&mut self) -> Result<()> {
+    async fn disconnect_players(&mut self, reason: Option<String>) -> Result<()> {
         let _ = self
             .broadcast_tx
-            .send(ServerBroadcast::Disconnect)
+            .send(ServerBroadcast::Disconnect { reason })
             .with_context(|| "Failed to broadcast disconnect signal");
         while !self.clients_tx.is_empty() {
             log::info!(
@@ -200,18 +815,22 @@ impl Server {
     /// 4. Validates the choice.
     /// 5. Applies the move to the game state.
     /// 6. Broadcasts the move to all players.
-    async fn handle_turn(&mut self, game: &mut Game, current_player: Player) -> Result<GameStatus> {
+    ///
+    /// If a disconnected player fails to reconnect within the grace period,
+    /// the turn ends early with that player forfeiting the game.
+    async fn handle_turn(&mut self, current_player: Player) -> Result<TurnOutcome> {
         log::debug!("Player {current_player} turn");
 
         // Calculate available moves
-        let movements: Vec<MovementIndices> = game
+        let movements: Vec<MovementIndices> = self
+            .game
             .iter_available_moves()
             .map(|movement| (&movement).into())
             .unique()
             .collect();
 
         // If player is disconnected, wait for reconnection logic to trigger in loop
-        if !self.disconnected.contains(&current_player) {
+        if !self.disconnected.contains_key(&current_player) {
             // Send turn message to current player
             self.clients_tx
                 .get_mut(&current_player)
@@ -227,17 +846,59 @@ impl Server {
 
         // Message receiving loop
         loop {
+            // Disconnected player with the earliest reconnection deadline
+            let forfeit = self
+                .disconnected
+                .iter()
+                .min_by_key(|(_, deadline)| **deadline)
+                .map(|(player, deadline)| (*player, *deadline));
+            let forfeit_deadline = forfeit.map_or_else(Instant::now, |(_, deadline)| deadline);
+
             tokio::select! {
 
+                // Disconnected player failed to reconnect in time
+                _ = tokio::time::sleep_until(forfeit_deadline), if forfeit.is_some() => {
+                    let (player, _) = forfeit.expect("Guarded by select precondition");
+                    if let Some(kind) = self.config.replace_absent {
+                        log::warn!("Player {player} did not reconnect in time, replacing them with a {kind} bot");
+                        self.seat_bot(kind, player)?;
+                        self.synchronize_player(player).await;
+                        if player == current_player {
+                            self.clients_tx
+                                .get_mut(&current_player)
+                                .expect("Bot just seated")
+                                .send(ServerMessage::Turn {
+                                    movements: movements.clone(),
+                                })
+                                .await
+                                .with_context(|| format!("Failed to send turn message to player {current_player}"))?;
+                        }
+                        continue;
+                    }
+                    log::warn!("Player {player} did not reconnect in time and forfeits the game");
+                    let status = self.game.status();
+                    return Ok(TurnOutcome::Ended(GameResult::Forfeit {
+                        winner: player.opponent(),
+                        total_turns: status.turns(),
+                        scores: status.scores(),
+                    }));
+                }
+
                 // Message from main threat
                 main_msg = self.main_rx.recv() => {
                    match main_msg {
                         None => bail!("Channel from main thread closed"),
                         Some(MainThreadMessage::ClientReconnected(player, tx)) => {
                             // Resume disconnected player
-                            if self.disconnected.remove(&player) {
+                            if self.disconnected.remove(&player).is_some() {
                                 log::info!("Player {player} reconnected");
                                 self.clients_tx.insert(player, tx);
+                                self.publish_snapshot();
+                                let _ = self
+                                    .broadcast_tx
+                                    .send(ServerBroadcast::PlayerReconnected { player });
+
+                                self.synchronize_player(player).await;
 
                                 // Resend turn if it is their turn
                                 if player == current_player {
@@ -254,19 +915,18 @@ impl Server {
                                 log::warn!("Player {player} reconnected but was not marked as disconnected");
                             }
                         }
-                         Some(MainThreadMessage::ClientConnected(..)) => {
-                             log::warn!("New client connected during game loop - ignored");
+                         Some(MainThreadMessage::ClientConnected(reservation, _, _, _, _, resp_tx)) => {
+                             log::warn!("New client tried to take seat {player} during game loop - refused", player = reservation.player);
+                             let _ = resp_tx.send(Err("The game is already in progress".to_string()));
                          }
                          Some(MainThreadMessage::ClientReconnectedHandle(uuid, resp_tx)) => {
                              // Check if session exists
                              let player = self.sessions.get(&uuid).copied();
                              let _ = resp_tx.send(player);
                          }
-                         Some(MainThreadMessage::RequestFreePlayer(resp_tx)) => {
-                             let free_player = [Player::Player1, Player::Player2]
-                                .into_iter()
-                                .find(|p| !self.clients_tx.contains_key(p));
-                            let _ = resp_tx.send(free_player);
+                         Some(MainThreadMessage::ReserveSeat(_, resp_tx)) => {
+                             // Seats of disconnected players are kept for their sessions
+                             let _ = resp_tx.send(None);
                         }
                     }
                 }
@@ -286,44 +946,134 @@ impl Server {
                             match message.request {
                                 // Client will disconnect
                                 ClientRequest::Disconnect => {
-                                    log::info!("Player {player} disconnected mid-game");
-                                    self.clients_tx.remove(&player);
-                                    self.disconnected.insert(player);
+                                    if self.clients_tx.remove(&player).is_none() {
+                                        log::warn!("Player {player} was already disconnected");
+                                        continue;
+                                    }
+                                    let grace_period = self.config.disconnect_timeout;
+                                    log::info!(
+                                        "Player {player} disconnected mid-game, waiting {secs} seconds for reconnection",
+                                        secs = grace_period.as_secs()
+                                    );
+                                    self.disconnected.insert(player, Instant::now() + grace_period);
+                                    self.publish_snapshot();
+                                    let _ = self
+                                        .broadcast_tx
+                                        .send(ServerBroadcast::PlayerDisconnected { player, grace_period });
                                     // We continue waiting for other players or reconnection
                                     // This pauses the turn if it was their turn, until they reconnect or timeout
                                 }
 
+                                // Movements are only accepted from the current player
+                                ClientRequest::Choice { .. }
+                                | ClientRequest::Move { .. }
+                                | ClientRequest::MovePath { .. }
+                                    if player != current_player =>
+                                {
+                                    log::error!("Player {player} attempted to move out of turn");
+                                    self.send_error(
+                                        player,
+                                        ErrorCode::NotYourTurn,
+                                        "It is not your turn".to_string(),
+                                        None,
+                                    )
+                                    .await?;
+                                }
+
                                 // Client chose a movement
                                 ClientRequest::Choice { movement_index } => {
-
-                                    // Check if player is the current player
-                                    if player != current_player {
-                                        log::error!("Player {player} attempted to move out of turn");
-                                        // TODO: Inform player of the out of turn movement
-                                        continue;
-                                    }
-
                                     // Validate movement index
                                     let movement = match movements.get(movement_index) {
-                                        Some(m) => m,
+                                        Some(m) => *m,
                                         None => {
                                              log::warn!("Player {player} sent invalid movement index: {movement_index}");
-                                             // TODO: Inform player
+                                             self.send_error(
+                                                 player,
+                                                 ErrorCode::InvalidMovementIndex,
+                                                 format!("Invalid movement index {movement_index}"),
+                                                 Some(format!("{n} movements available", n = movements.len())),
+                                             )
+                                             .await?;
                                              continue;
                                         }
                                     };
 
-                                    log::debug!("Player {player} chose movement {movement:?}");
+                                    return self.play_movement(player, movement);
+                                }
+
+                                // Client submitted a movement by coordinates
+                                ClientRequest::Move { movement } => {
+                                    // Validate movement against the available movements
+                                    if !movements.contains(&movement) {
+                                        log::warn!("Player {player} sent unavailable movement {movement:?}");
+                                        self.send_error(
+                                            player,
+                                            ErrorCode::InvalidMovement,
+                                            "Movement is not available".to_string(),
+                                            None,
+                                        )
+                                        .await?;
+                                        continue;
+                                    }
+
+                                    return self.play_movement(player, movement);
+                                }
+
+                                // Client submitted a movement by its hopping path
+                                ClientRequest::MovePath { path } => {
+                                    // Validate path against the rules of the game
+                                    let movement = match self.game.validate_movement(&Movement::Hops { path }) {
+                                        Ok(m) => m,
+                                        Err(e) => {
+                                            log::warn!("Player {player} sent invalid hopping path: {e:?}");
+                                            self.send_error(
+                                                player,
+                                                ErrorCode::InvalidMovement,
+                                                format!("Invalid hopping path: {e}"),
+                                                None,
+                                            )
+                                            .await?;
+                                            continue;
+                                        }
+                                    };
+                                    // Only movements of the turn are applied unchecked, whatever the path validation says
+                                    if !movements.contains(&movement) {
+                                        log::error!("Player {player} sent hopping path {movement:?} missing from the available movements");
+                                        self.send_error(
+                                            player,
+                                            ErrorCode::InvalidMovement,
+                                            "Movement is not available".to_string(),
+                                            None,
+                                        )
+                                        .await?;
+                                        continue;
+                                    }
 
-                                    // Apply chosen movement
-                                    // Validated by index selection
-                                    let status = unsafe { game.apply_movement_unchecked(movement) };
+                                    return self.play_movement(player, movement);
+                                }
 
-                                    // Broadcast movement to all players
-                                    self.broadcast_tx.send(ServerBroadcast::Movement {player,movement: *movement, scores: status.scores() }).with_context(|| "Failed to broadcast movement")?;
+                                // Client sent a chat message
+                                ClientRequest::Chat { text } => {
+                                    self.handle_chat(player, text).await?;
+                                }
 
-                                    return Ok(status);
+                                // The next game is only chosen once this one is over
+                                request @ (ClientRequest::Rematch | ClientRequest::ReturnToLobby) => {
+                                    log::warn!("Player {player} sent {request:?} during the game");
+                                    self.send_error(
+                                        player,
+                                        ErrorCode::UnexpectedMessage,
+                                        "The game is not over yet".to_string(),
+                                        None,
+                                    )
+                                    .await?;
+                                }
 
+                                // Client resigned, offered or answered an offer
+                                request => {
+                                    if let Some(outcome) = self.handle_negotiation(player, request).await? {
+                                        return Ok(outcome);
+                                    }
                                 }
                             }
                         },
@@ -335,6 +1085,246 @@ impl Server {
         }
     }
 
+    /// Handles resignations, offers and answers to offers
+    ///
+    /// Players can negotiate at any point of the game, regardless of whose turn it is.
+    /// A player can only have one offer pending at a time, and must answer the opponent's
+    /// offer before making one of their own.
+    ///
+    /// Returns the outcome of the turn if the request interrupts it.
+    async fn handle_negotiation(
+        &mut self,
+        player: Player,
+        request: ClientRequest,
+    ) -> Result<Option<TurnOutcome>> {
+        let status = self.game.status();
+        let opponent_offer = self
+            .offer
+            .filter(|(by, _)| *by == player.opponent())
+            .map(|(_, offer)| offer);
+
+        match request {
+            // Player gave up
+            ClientRequest::Resign => {
+                log::info!("Player {player} resigned");
+                return Ok(Some(TurnOutcome::Ended(GameResult::Resigned {
+                    winner: player.opponent(),
+                    total_turns: status.turns(),
+                    scores: status.scores(),
+                })));
+            }
+
+            // New offers
+            ClientRequest::OfferDraw | ClientRequest::RequestTakeback
+                if opponent_offer.is_some() =>
+            {
+                log::warn!("Player {player} made an offer while one from the opponent is pending");
+                self.send_error(
+                    player,
+                    ErrorCode::OfferPending,
+                    "Answer the pending offer of your opponent first".to_string(),
+                    None,
+                )
+                .await?;
+            }
+            ClientRequest::OfferDraw => {
+                log::info!("Player {player} offered a draw");
+                self.offer = Some((player, Offer::Draw));
+                let _ = self
+                    .broadcast_tx
+                    .send(ServerBroadcast::DrawOffered { player });
+            }
+            ClientRequest::RequestTakeback => {
+                if !self.game.iter_history().any(|(by, _)| by == player) {
+                    self.send_error(
+                        player,
+                        ErrorCode::NothingToTakeBack,
+                        "You have not made any movement yet".to_string(),
+                        None,
+                    )
+                    .await?;
+                    return Ok(None);
+                }
+                log::info!("Player {player} requested a takeback");
+                self.offer = Some((player, Offer::Takeback));
+                let _ = self
+                    .broadcast_tx
+                    .send(ServerBroadcast::TakebackRequested { player });
+            }
+
+            // Answers to the opponent's offer
+            ClientRequest::AcceptDraw if opponent_offer == Some(Offer::Draw) => {
+                log::info!("Player {player} accepted the draw");
+                self.offer = None;
+                return Ok(Some(TurnOutcome::Ended(GameResult::Draw {
+                    total_turns: status.turns(),
+                    scores: status.scores(),
+                })));
+            }
+            ClientRequest::DeclineDraw if opponent_offer == Some(Offer::Draw) => {
+                log::info!("Player {player} declined the draw");
+                self.offer = None;
+                let _ = self
+                    .broadcast_tx
+                    .send(ServerBroadcast::DrawDeclined { player });
+            }
+            ClientRequest::AcceptTakeback if opponent_offer == Some(Offer::Takeback) => {
+                log::info!("Player {player} accepted the takeback");
+                self.offer = None;
+                return self.take_back(player.opponent()).map(Some);
+            }
+            ClientRequest::DeclineTakeback if opponent_offer == Some(Offer::Takeback) => {
+                log::info!("Player {player} declined the takeback");
+                self.offer = None;
+                let _ = self
+                    .broadcast_tx
+                    .send(ServerBroadcast::TakebackDeclined { player });
+            }
+            ClientRequest::AcceptDraw
+            | ClientRequest::DeclineDraw
+            | ClientRequest::AcceptTakeback
+            | ClientRequest::DeclineTakeback => {
+                log::warn!("Player {player} answered an offer that was not made: {request:?}");
+                self.send_error(
+                    player,
+                    ErrorCode::NoPendingOffer,
+                    "There is no such offer to answer".to_string(),
+                    None,
+                )
+                .await?;
+            }
+
+            // Handled by the turn loop
+            request => bail!("Unexpected request from player {player}: {request:?}"),
+        }
+
+        Ok(None)
+    }
+
+    /// Checks a chat message and broadcasts it to all players
+    ///
+    /// Messages are rejected if chat is muted in the room, if they are empty or longer than
+    /// [`CHAT_MESSAGE_LENGTH`], or if the player sent more than [`CHAT_RATE_LIMIT`] messages
+    /// within [`CHAT_RATE_WINDOW`].
+    async fn handle_chat(&mut self, player: Player, text: String) -> Result<()> {
+        if self.config.mute_chat {
+            return self
+                .send_error(
+                    player,
+                    ErrorCode::ChatMuted,
+                    "Chat is muted in this room".to_string(),
+                    None,
+                )
+                .await;
+        }
+
+        if text.trim().is_empty() || text.len() > CHAT_MESSAGE_LENGTH {
+            log::warn!(
+                "Player {player} sent a chat message of {len} bytes",
+                len = text.len()
+            );
+            return self
+                .send_error(
+                    player,
+                    ErrorCode::InvalidChatMessage,
+                    "Chat message is empty or too long".to_string(),
+                    Some(format!("At most {CHAT_MESSAGE_LENGTH} bytes are allowed")),
+                )
+                .await;
+        }
+
+        // Forget messages that left the rate limiting window
+        let now = Instant::now();
+        let times = self.chat_times.entry(player).or_default();
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= CHAT_RATE_WINDOW)
+        {
+            times.pop_front();
+        }
+        if times.len() >= CHAT_RATE_LIMIT {
+            log::warn!("Player {player} exceeded the chat rate limit");
+            return self
+                .send_error(
+                    player,
+                    ErrorCode::ChatRateLimited,
+                    "Too many chat messages".to_string(),
+                    Some(format!(
+                        "At most {CHAT_RATE_LIMIT} messages every {secs} seconds are allowed",
+                        secs = CHAT_RATE_WINDOW.as_secs()
+                    )),
+                )
+                .await;
+        }
+        times.push_back(now);
+
+        let timestamp = now_millis();
+        let _ = self.broadcast_tx.send(ServerBroadcast::Chat {
+            player,
+            text,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Undoes movements until the last movement of the player is taken back
+    ///
+    /// The game resumes with the turn of the player.
+    fn take_back(&mut self, player: Player) -> Result<TurnOutcome> {
+        let mut movements = Vec::with_capacity(Player::count());
+        loop {
+            let (by, movement) = self
+                .game
+                .undo_movement()
+                .ok_or(anyhow!("Player {player} has no movement to take back"))?;
+            movements.push(movement);
+            if by == player {
+                break;
+            }
+        }
+        log::debug!("Took back movements {movements:?}");
+        self.journal(JournalRecord::TakenBack {
+            count: movements.len(),
+        })?;
+
+        self.broadcast_tx
+            .send(ServerBroadcast::TakenBack {
+                player,
+                movements,
+                scores: self.game.status().scores(),
+            })
+            .with_context(|| "Failed to broadcast takeback")?;
+
+        Ok(TurnOutcome::TakenBack)
+    }
+
+    /// Applies a validated movement and broadcasts it to all players
+    fn play_movement(&mut self, player: Player, movement: MovementIndices) -> Result<TurnOutcome> {
+        log::debug!("Player {player} chose movement {movement:?}");
+
+        // Pending offers lapse once the game moves on
+        self.offer = None;
+
+        // Record the movement before announcing it
+        self.journal(JournalRecord::Movement { player, movement })?;
+
+        // Apply chosen movement
+        // Validated by the caller
+        let status = unsafe { self.game.apply_movement_unchecked(&movement) };
+
+        // Broadcast movement to all players
+        self.broadcast_tx
+            .send(ServerBroadcast::Movement {
+                player,
+                movement,
+                scores: status.scores(),
+            })
+            .with_context(|| "Failed to broadcast movement")?;
+
+        Ok(TurnOutcome::Played)
+    }
+
     /// Runs the main game loop
     ///
     /// Manages the state machine of the game:
@@ -343,15 +1333,12 @@ impl Server {
     /// - Delegates turn handling to `handle_turn`.
     /// - Updates game timer and logs progress.
     async fn game_loop(&mut self, max_turns: usize) -> Result<GameResult> {
-        // Create game
-        let mut game = Game::new();
-
         // Game timer
         let mut game_timer = GameTimer::<256>::new();
 
         // Game loop
         loop {
-            match game.status() {
+            match self.game.status() {
                 // Game has finished
                 GameStatus::Finished {
                     winner,
@@ -382,12 +1369,23 @@ impl Server {
                     }
 
                     // Handle turn
-                    self.handle_turn(&mut game, current_player)
+                    let turn_start = Instant::now();
+                    let outcome = self
+                        .handle_turn(current_player)
                         .await
                         .with_context(|| "Falied to handle game turn")?;
+                    metrics()
+                        .turn_duration
+                        .observe(turn_start.elapsed().as_secs_f64());
+                    if let TurnOutcome::Ended(result) = outcome {
+                        return Ok(result);
+                    }
+                    self.publish_snapshot();
 
                     // Update timing
-                    game_timer.on_trigger(&game, |timer| {
+                    let game = &self.game;
+                    game_timer.on_trigger(game, |timer| {
+                        metrics().turn_rate.observe(timer.turns_rate());
                         // Calculate size of game history in memory
                         let hist_size = format_size(game.history_bytes(), BINARY);
                         // Log information
@@ -407,29 +1405,251 @@ impl Server {
 
     /// Main server thread loop
     ///
-    /// 1. Waits for players to connect.
+    /// 1. Waits for players to connect, unless the game was recovered from its journal.
     /// 2. Runs the game loop.
     /// 3. Broadcasts the game result.
-    async fn run(&mut self, timeout: Duration, max_turns: usize) -> Result<()> {
+    ///
+    /// Persistent rooms then start over with the next game, either a rematch or a new game
+    /// once the lobby is full again, until the server shuts down.
+    async fn run(&mut self) -> Result<()> {
         log::trace!("Server thread started");
+        let timeout = self.config.connection_timeout;
 
-        // Wait for players to connect
-        let n_players = Player::count();
-        log::info!(
-            "Waiting {timeout_secs} seconds for {n_players} players to connect...",
-            timeout_secs = timeout.as_secs()
-        );
-        tokio::time::timeout(timeout, self.wait_players_connect(n_players))
-            .await
-            .with_context(|| "Timed out waiting for players to connect")?
-            .with_context(|| "Failed to wait for players to connect")?;
+        // Bots of a recovered game pick up where they left
+        for (player, kind) in std::mem::take(&mut self.recovered_bots) {
+            log::info!("Seating {kind} bot back as player {player}");
+            self.spawn_bot(kind, player);
+            self.synchronize_player(player).await;
+        }
+        self.publish_snapshot();
 
-        // Main game loop
-        match self
-            .game_loop(max_turns)
-            .await
-            .with_context(|| "Game loop encountered an error")?
-        {
+        loop {
+            // Wait for players to connect, unless the game was recovered in progress or is a rematch
+            if self.status == RoomStatus::Waiting {
+                let n_players = Player::count();
+                if self.config.persistent {
+                    log::info!("Waiting for {n_players} players to connect...");
+                    self.wait_players_connect(n_players)
+                        .await
+                        .with_context(|| "Failed to wait for players to connect")?;
+                } else {
+                    log::info!(
+                        "Waiting {timeout_secs} seconds for {n_players} players to connect...",
+                        timeout_secs = timeout.as_secs()
+                    );
+                    tokio::time::timeout(timeout, self.wait_players_connect(n_players))
+                        .await
+                        .with_context(|| "Timed out waiting for players to connect")?
+                        .with_context(|| "Failed to wait for players to connect")?;
+                }
+            }
+
+            // Main game loop
+            let active_game = metrics().active_game();
+            let result = self
+                .game_loop(self.config.max_turns)
+                .await
+                .with_context(|| "Game loop encountered an error")?;
+            drop(active_game);
+            self.finish_game(result).await?;
+
+            if !self.config.persistent {
+                return Ok(());
+            }
+            let next_game = self
+                .wait_next_game()
+                .await
+                .with_context(|| "Failed to wait for the next game")?;
+            self.start_next_game(next_game).await?;
+        }
+    }
+
+    /// Waits for the players of a finished game to choose the next one
+    ///
+    /// The next game is a rematch once every player asked for it. The room returns to the lobby
+    /// as soon as a player leaves their seat, disconnects, or was already disconnected.
+    async fn wait_next_game(&mut self) -> Result<NextGame> {
+        if !self.disconnected.is_empty() {
+            return Ok(NextGame::Lobby);
+        }
+
+        loop {
+            tokio::select! {
+                // Message from main thread
+                main_msg = self.main_rx.recv() => {
+                    match main_msg.ok_or(anyhow!("Channel from main thread closed"))? {
+                        MainThreadMessage::ClientConnected(reservation, _, _, _, _, resp_tx) => {
+                            log::warn!("New client tried to take seat {player} after the game - refused", player = reservation.player);
+                            let _ = resp_tx.send(Err("The game is over".to_string()));
+                        }
+                        MainThreadMessage::ClientReconnected(player, _) => {
+                            log::warn!("Player {player} reconnected but was not marked as disconnected");
+                        }
+                        MainThreadMessage::ClientReconnectedHandle(uuid, resp_tx) => {
+                            let player = self.sessions.get(&uuid).copied();
+                            let _ = resp_tx.send(player);
+                        }
+                        MainThreadMessage::ReserveSeat(_, resp_tx) => {
+                            // Seats are kept for the players of the finished game
+                            let _ = resp_tx.send(None);
+                        }
+                    }
+                }
+
+                // Message from client thread
+                client_msg = self.clients_rx.recv() => {
+                    let ClientMessage { player, request } =
+                        client_msg.ok_or(anyhow!("Channel from clients closed"))?;
+                    match request {
+                        ClientRequest::Disconnect => {
+                            if self.clients_tx.remove(&player).is_none() {
+                                log::warn!("Player {player} was already disconnected");
+                                continue;
+                            }
+                            let grace_period = self.config.disconnect_timeout;
+                            log::info!(
+                                "Player {player} left after the game, holding their seat in the lobby for {secs} seconds",
+                                secs = grace_period.as_secs()
+                            );
+                            self.disconnected.insert(player, Instant::now() + grace_period);
+                            let _ = self
+                                .broadcast_tx
+                                .send(ServerBroadcast::PlayerDisconnected { player, grace_period });
+                            return Ok(NextGame::Lobby);
+                        }
+                        ClientRequest::Rematch => {
+                            if !self.rematch.insert(player) {
+                                log::warn!("Player {player} already asked for a rematch");
+                                continue;
+                            }
+                            log::info!("Player {player} asked for a rematch");
+                            let _ = self
+                                .broadcast_tx
+                                .send(ServerBroadcast::RematchRequested { player });
+                            if self.rematch.len() == Player::count() {
+                                return Ok(NextGame::Rematch);
+                            }
+                        }
+                        ClientRequest::ReturnToLobby => {
+                            log::info!("Player {player} returned to the lobby");
+                            self.leave_seat(player).await?;
+                            return Ok(NextGame::Lobby);
+                        }
+                        ClientRequest::Chat { text } => self.handle_chat(player, text).await?,
+                        request => {
+                            log::warn!("Player {player} sent {request:?} after the game");
+                            self.send_error(
+                                player,
+                                ErrorCode::UnexpectedMessage,
+                                "The game is over".to_string(),
+                                None,
+                            )
+                            .await?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Frees the seat of a connected player who returns to the lobby, closing their connection
+    async fn leave_seat(&mut self, player: Player) -> Result<()> {
+        if let Some(client_tx) = self.clients_tx.remove(&player) {
+            let _ = client_tx
+                .send(ServerMessage::Disconnect {
+                    reason: Some(LOBBY_REASON.to_string()),
+                })
+                .await;
+        }
+        self.sessions.retain(|_, seated| *seated != player);
+        self.names.remove(&player);
+        self.chat_times.remove(&player);
+        self.journal(JournalRecord::Left { player })?;
+        let _ = self
+            .broadcast_tx
+            .send(ServerBroadcast::PlayerLeft { player });
+        Ok(())
+    }
+
+    /// Resets the room for the next game of a persistent room
+    ///
+    /// A rematch starts right away, with every player taking the seat of their opponent
+    /// so that the other player moves first. Otherwise the room returns to the lobby.
+    async fn start_next_game(&mut self, next_game: NextGame) -> Result<()> {
+        let swapped = next_game == NextGame::Rematch;
+        if swapped {
+            self.swap_seats();
+        }
+        self.game_id = Uuid::new_v4();
+        self.journal(JournalRecord::NewGame {
+            id: self.game_id,
+            swapped,
+        })?;
+        self.game = Game::new();
+        self.offer = None;
+        self.rematch.clear();
+        self.result = None;
+        self.finished_at = None;
+        match next_game {
+            NextGame::Rematch => {
+                log::info!("Starting rematch {id}", id = self.game_id);
+                self.status = RoomStatus::Playing;
+                self.started_at = Some(now_millis());
+            }
+            NextGame::Lobby => {
+                log::info!("Returning to the lobby for game {id}", id = self.game_id);
+                self.status = RoomStatus::Waiting;
+                self.started_at = None;
+            }
+        }
+        self.publish_snapshot();
+
+        // Players still seated see the new board, and the settings of the rematch
+        for player in self.clients_tx.keys().copied().collect::<Vec<_>>() {
+            if let Err(e) = self.send_game_state(player).await {
+                log::error!("Failed to synchronize player {player}: {e:?}");
+            }
+            if swapped && let Err(e) = self.send_game_started(player).await {
+                log::error!("Failed to announce game start to player {player}: {e:?}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives every player the seat of their opponent
+    ///
+    /// Clients are told before any message about the next game, so that they switch perspective first.
+    fn swap_seats(&mut self) {
+        fn swap<T>(seats: &mut HashMap<Player, T>) {
+            *seats = seats
+                .drain()
+                .map(|(player, value)| (player.opponent(), value))
+                .collect();
+        }
+        swap(&mut self.clients_tx);
+        swap(&mut self.names);
+        swap(&mut self.chat_times);
+        for player in self.sessions.values_mut() {
+            *player = player.opponent();
+        }
+        let _ = self.broadcast_tx.send(ServerBroadcast::SeatsSwapped);
+    }
+
+    /// Records the result of the game and broadcasts it to all players
+    ///
+    /// The game is archived once the players are told, if an archive is set.
+    /// The journal is then deleted, unless the room goes on with another game.
+    async fn finish_game(&mut self, result: GameResult) -> Result<()> {
+        metrics().game_finished(&result);
+        self.journal(JournalRecord::Finished {
+            result: result.clone(),
+        })?;
+        self.status = RoomStatus::Finished;
+        self.result = Some(result.clone());
+        self.finished_at = Some(now_millis());
+        self.publish_snapshot();
+
+        match result {
             GameResult::MaxTurns {
                 total_turns,
                 scores,
@@ -461,22 +1681,165 @@ impl Server {
                     })
                     .with_context(|| "Failed to broadcast game finished message")?;
             }
+            GameResult::Forfeit {
+                winner,
+                total_turns,
+                scores,
+            } => {
+                log::info!(
+                    "Game finished by forfeit, player {winner} won after {total_turns} turns"
+                );
+                self.broadcast_tx
+                    .send(ServerBroadcast::GameFinished {
+                        result: GameResult::Forfeit {
+                            winner,
+                            total_turns,
+                            scores,
+                        },
+                    })
+                    .with_context(|| "Failed to broadcast forfeit message")?;
+            }
+            GameResult::Resigned {
+                winner,
+                total_turns,
+                scores,
+            } => {
+                log::info!(
+                    "Game finished by resignation, player {winner} won after {total_turns} turns"
+                );
+                self.broadcast_tx
+                    .send(ServerBroadcast::GameFinished {
+                        result: GameResult::Resigned {
+                            winner,
+                            total_turns,
+                            scores,
+                        },
+                    })
+                    .with_context(|| "Failed to broadcast resignation message")?;
+            }
+            GameResult::Draw {
+                total_turns,
+                scores,
+            } => {
+                log::info!("Game finished in a draw by agreement after {total_turns} turns");
+                self.broadcast_tx
+                    .send(ServerBroadcast::GameFinished {
+                        result: GameResult::Draw {
+                            total_turns,
+                            scores,
+                        },
+                    })
+                    .with_context(|| "Failed to broadcast draw message")?;
+            }
+            GameResult::Aborted {
+                total_turns,
+                scores,
+            } => {
+                log::warn!("Game aborted by server shutdown after {total_turns} turns");
+                self.broadcast_tx
+                    .send(ServerBroadcast::GameFinished {
+                        result: GameResult::Aborted {
+                            total_turns,
+                            scores,
+                        },
+                    })
+                    .with_context(|| "Failed to broadcast abort message")?;
+            }
+        }
+
+        // Archive the game
+        if let Some(archive) = self.archive.clone()
+            && let Some(game) = ArchivedGame::new(
+                self.game_id,
+                self.created_at,
+                &self.config,
+                &self.snapshot(),
+            )
+        {
+            let id = self.game_id;
+            match tokio::task::spawn_blocking(move || archive.store(&game)).await {
+                Ok(Ok(())) => log::info!("Archived game {id}"),
+                Ok(Err(e)) => log::error!("Failed to archive game {id}: {e:?}"),
+                Err(e) => log::error!("Archiving task of game {id} failed: {e:?}"),
+            }
+        }
+
+        // Persistent rooms keep journaling their next games
+        if !self.config.persistent {
+            self.remove_journal();
         }
 
         Ok(())
     }
 
+    /// Deletes the journal of the room, if enabled, once there is nothing left to recover
+    fn remove_journal(&mut self) {
+        if let Some(journal) = self.journal.take()
+            && let Err(e) = journal.remove()
+        {
+            log::error!(
+                "Failed to remove journal of game {id}: {e:?}",
+                id = self.game_id
+            );
+        }
+    }
+
+    /// Ends the game in progress, if any, because the server is shutting down
+    async fn abort_game(&mut self) -> Result<()> {
+        if self.status != RoomStatus::Playing {
+            return Ok(());
+        }
+        let status = self.game.status();
+        self.finish_game(GameResult::Aborted {
+            total_turns: status.turns(),
+            scores: status.scores(),
+        })
+        .await
+    }
+
     /// Server thread run wrapper
     ///
     /// Entry point for the server thread.
     /// Runs the server and ensures all players are disconnected when it finishes.
-    pub async fn try_run(mut self, timeout: Duration, max_turns: usize) -> Result<()> {
-        // Attempt to run server
-        let result = self.run(timeout, max_turns).await;
+    /// If the server is shut down first, the game in progress is aborted,
+    /// unless it is journaled, in which case it is left unfinished to be resumed on the next start.
+    pub async fn try_run(mut self) -> Result<()> {
+        // Attempt to run server, unless shut down in the meantime
+        let shutdown = self.shutdown.clone();
+        let outcome = tokio::select! {
+            result = self.run() => Some(result),
+            _ = shutdown.cancelled() => None,
+        };
+        let suspended =
+            outcome.is_none() && self.journal.is_some() && self.status == RoomStatus::Playing;
+        let (result, reason) = match outcome {
+            Some(result) => (result, None),
+            None if suspended => {
+                log::info!(
+                    "Shutting down the server, game {id} is resumed on the next start",
+                    id = self.game_id
+                );
+                (Ok(()), Some(SUSPEND_REASON.to_string()))
+            }
+            None => {
+                log::info!("Shutting down the server");
+                (self.abort_game().await, Some(SHUTDOWN_REASON.to_string()))
+            }
+        };
+        if self.status != RoomStatus::Finished && !suspended {
+            self.status = RoomStatus::Closed;
+            self.publish_snapshot();
+            if let Err(e) = self.journal(JournalRecord::Closed) {
+                log::error!("Failed to record closing of the game: {e:?}");
+            }
+        }
+        if !suspended {
+            self.remove_journal();
+        }
 
         // Disconnect all players
         log::info!("Disconnecting all players");
-        if let Err(e) = self.disconnect_players().await {
+        if let Err(e) = self.disconnect_players(reason).await {
             log::error!("Failed to disconnect all players: {e:?}");
         }
 
@@ -8,14 +8,16 @@
 //! - [`RemoteInMessage`]: Messages sent from Remote Client to Server.
 //!
 //! ## Codecs
-//! It also includes `tokio_util` codecs ([`ServerCodec`], [`ClientCodec`]) for framing and serialization (CBOR).
+//! It also includes `tokio_util` codecs ([`ServerCodec`], [`ClientCodec`]) for framing and serialization.
+//! Messages are encoded either as length-delimited CBOR or as newline-delimited JSON (see [`Encoding`]).
 
-use crate::sternhalma::board::{movement::MovementIndices, player::Player};
-use crate::sternhalma::{GameResult, Scores};
+use crate::sternhalma::board::{HexIdx, movement::MovementIndices, player::Player};
+use crate::sternhalma::{GameResult, Scores, Variant};
 use anyhow::{Context, Result};
 use bytes::{Bytes, BytesMut};
-use serde::{Deserialize, Serialize};
-use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec, LinesCodec};
 use uuid::Uuid;
 
 /// Maximum length of a remote message in bytes
@@ -23,6 +25,124 @@ use uuid::Uuid;
 /// This limits the size of individual messages to prevent DoS attacks.
 pub const REMOTE_MESSAGE_LENGTH: usize = 4 * 1024;
 
+/// Maximum length of a chat message in bytes
+///
+/// Leaves room for the JSON escaping of the text, and for the sender and timestamp of the broadcast,
+/// within [`REMOTE_MESSAGE_LENGTH`].
+pub const CHAT_MESSAGE_LENGTH: usize = REMOTE_MESSAGE_LENGTH / 4;
+
+/// Version of the remote protocol implemented by the server
+///
+/// Version 1 is the original protocol, in which `Hello` carried no fields.
+pub const PROTOCOL_VERSION: u32 = 6;
+
+/// Oldest protocol version the server still accepts
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional protocol features supported by the server
+pub const SUPPORTED_CAPABILITIES: &[Capability] = &[Capability::CoordinateMoves];
+
+/// Optional protocol features
+///
+/// Requested by the client in `Hello` and echoed back in `Welcome` if enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Movements can be submitted by coordinates (`Move` and `MovePath`)
+    CoordinateMoves,
+    /// Capability not known to this server
+    ///
+    /// Never enabled; allows clients to request features from newer servers.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Protocol version and capabilities negotiated with a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiation {
+    /// Highest protocol version supported by both sides
+    pub protocol_version: u32,
+    /// Capabilities requested by the client and supported by the server
+    pub capabilities: Vec<Capability>,
+}
+
+impl Negotiation {
+    /// Whether the capability was negotiated
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+/// Negotiates the protocol version and capabilities requested by a client
+///
+/// The negotiated version is the highest version supported by both sides.
+/// Returns the reason for rejection if the client only speaks versions older than [`MIN_PROTOCOL_VERSION`].
+pub fn negotiate(
+    protocol_version: u32,
+    capabilities: &[Capability],
+) -> Result<Negotiation, String> {
+    if protocol_version < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "Unsupported protocol version {protocol_version} (supported: {MIN_PROTOCOL_VERSION} to {PROTOCOL_VERSION})"
+        ));
+    }
+    let capabilities = capabilities
+        .iter()
+        .copied()
+        .filter(|capability| SUPPORTED_CAPABILITIES.contains(capability))
+        .unique()
+        .collect();
+    Ok(Negotiation {
+        protocol_version: protocol_version.min(PROTOCOL_VERSION),
+        capabilities,
+    })
+}
+
+/// Protocol version assumed when the client does not send one
+const fn legacy_protocol_version() -> u32 {
+    1
+}
+
+/// Error codes reported to remote clients
+///
+/// Sent along with [`RemoteOutMessage::Error`] so that clients can react to a rejected
+/// action programmatically. See `docs/protocol.md` for the full code table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The message could not be decoded
+    InvalidMessage,
+    /// The message is not valid at this point of the session
+    UnexpectedMessage,
+    /// A movement was submitted while it is not the player's turn
+    NotYourTurn,
+    /// The movement index is outside of the list of available movements
+    InvalidMovementIndex,
+    /// The movement submitted by coordinates is not legal
+    InvalidMovement,
+    /// The opponent has an offer waiting for an answer
+    OfferPending,
+    /// There is no offer from the opponent to answer
+    NoPendingOffer,
+    /// The player has no movement to take back
+    NothingToTakeBack,
+    /// Chat is muted in this room
+    ChatMuted,
+    /// The chat message is empty or too long
+    InvalidChatMessage,
+    /// The player sent too many chat messages in a short time
+    ChatRateLimited,
+}
+
+/// Opponent description sent to remote clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opponent {
+    /// Opponent in the client's perspective
+    pub player: Player,
+    /// Name announced by the opponent in its `Hello`
+    pub name: Option<String>,
+}
+
 /// Messages sent from the Server to a Remote Client
 ///
 /// This enum defines all possible messages that the server can send to a connected client
@@ -32,21 +152,30 @@ pub const REMOTE_MESSAGE_LENGTH: usize = 4 * 1024;
 pub enum RemoteOutMessage {
     /// Welcome message with session ID
     ///
-    /// Sent immediately after connection to assign a session ID.
+    /// Sent immediately after connection to assign a session ID,
+    /// along with the negotiated protocol version and enabled capabilities.
     ///
     /// # Design Decision
     /// The player identity is NOT sent here because the protocol ensures every client
     /// sees themselves as `Player1`. The server handles the mapping to the actual
     /// internal player identity.
-    Welcome { session_id: Uuid },
-    /// Reconnect reject
+    Welcome {
+        session_id: Uuid,
+        protocol_version: u32,
+        capabilities: Vec<Capability>,
+    },
+    /// Connection reject
     ///
-    /// Sent if a reconnection attempt fails (e.g., invalid session ID).
+    /// Sent if a connection attempt fails (e.g., server full, invalid session ID, unsupported protocol version).
     Reject { reason: String },
     /// Disconnection signal
     ///
     /// Sent to serve as a polite "goodbye" before closing the connection.
-    Disconnect,
+    Disconnect {
+        /// Why the server is closing the connection (e.g. shutting down)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
     /// Inform remote client that it is their turn
     Turn {
         /// List of available movements
@@ -61,8 +190,173 @@ pub enum RemoteOutMessage {
         movement: MovementIndices,
         scores: Scores,
     },
+    /// Inform remote client that an opponent lost connection
+    ///
+    /// The opponent forfeits unless they reconnect within `timeout_secs` seconds.
+    OpponentDisconnected { player: Player, timeout_secs: u64 },
+    /// Inform remote client that a disconnected opponent is back
+    OpponentReconnected { player: Player },
+    /// Inform remote client that the game has started
+    ///
+    /// Sent once all players have connected, and again after reconnecting mid-game.
+    GameStarted {
+        /// Whether the client made the first move of the game
+        you_move_first: bool,
+        opponents: Vec<Opponent>,
+        variant: Variant,
+        /// Maximum number of turns, if limited
+        max_turns: Option<usize>,
+    },
+    /// Full game state
+    ///
+    /// Sent after every `Welcome` so that the client can rebuild its view of the game.
+    GameState {
+        /// Pieces on the board
+        board: Vec<(HexIdx, Player)>,
+        /// Player to move, if the game is still ongoing
+        to_move: Option<Player>,
+        /// Number of turns played
+        turn: usize,
+        scores: Scores,
+        /// Movements played so far along with the player who made them
+        history: Vec<(Player, MovementIndices)>,
+    },
+    /// Inform remote client that a player offered a draw
+    DrawOffered { player: Player },
+    /// Inform remote client that a player declined a draw offer
+    DrawDeclined { player: Player },
+    /// Inform remote client that a player asked to take back their last movement
+    TakebackRequested { player: Player },
+    /// Inform remote client that a player declined a takeback request
+    TakebackDeclined { player: Player },
+    /// Inform remote client that movements were taken back
+    ///
+    /// The movements are listed most recent first and have to be reverted in that order.
+    /// The game resumes with the turn of `player`, who requested the takeback.
+    TakenBack {
+        player: Player,
+        movements: Vec<MovementIndices>,
+        scores: Scores,
+    },
+    /// Inform remote client about a chat message
+    ///
+    /// `timestamp` is the time the server received the message, in milliseconds since the Unix epoch.
+    Chat {
+        player: Player,
+        text: String,
+        timestamp: u64,
+    },
     /// Inform remote client that the game has finished with a result
     GameFinished { result: GameResult },
+    /// Inform remote client that a player asked for a rematch
+    ///
+    /// The next game starts with the seats swapped once every player asked for it.
+    RematchRequested { player: Player },
+    /// Inform remote client that an opponent left their seat after the game finished
+    ///
+    /// The room returns to the lobby until a new opponent takes the seat.
+    OpponentLeft { player: Player },
+    /// Inform remote client that one of its messages was rejected
+    ///
+    /// `context` carries optional details about the offending message.
+    Error {
+        code: ErrorCode,
+        message: String,
+        context: Option<String>,
+    },
+}
+
+impl RemoteOutMessage {
+    /// Protocol version that introduced the message
+    pub fn since_version(&self) -> u32 {
+        match self {
+            RemoteOutMessage::Welcome { .. }
+            | RemoteOutMessage::Reject { .. }
+            | RemoteOutMessage::Disconnect { .. }
+            | RemoteOutMessage::Turn { .. }
+            | RemoteOutMessage::Movement { .. }
+            | RemoteOutMessage::GameFinished { .. } => 1,
+            RemoteOutMessage::OpponentDisconnected { .. }
+            | RemoteOutMessage::OpponentReconnected { .. }
+            | RemoteOutMessage::GameStarted { .. }
+            | RemoteOutMessage::GameState { .. }
+            | RemoteOutMessage::Error { .. } => 2,
+            RemoteOutMessage::DrawOffered { .. }
+            | RemoteOutMessage::DrawDeclined { .. }
+            | RemoteOutMessage::TakebackRequested { .. }
+            | RemoteOutMessage::TakebackDeclined { .. }
+            | RemoteOutMessage::TakenBack { .. } => 3,
+            RemoteOutMessage::Chat { .. } => 4,
+            RemoteOutMessage::RematchRequested { .. } | RemoteOutMessage::OpponentLeft { .. } => 6,
+        }
+    }
+
+    /// Converts the message for a client speaking the given protocol version
+    ///
+    /// Returns `None` if the message did not exist in that version.
+    /// Fields and results introduced later are left out or replaced by their closest older equivalent.
+    pub fn downgrade(self, protocol_version: u32) -> Option<Self> {
+        if self.since_version() > protocol_version {
+            return None;
+        }
+        Some(match self {
+            RemoteOutMessage::Disconnect { .. } if protocol_version < 5 => {
+                RemoteOutMessage::Disconnect { reason: None }
+            }
+            RemoteOutMessage::GameFinished { result } => RemoteOutMessage::GameFinished {
+                result: downgrade_result(result, protocol_version),
+            },
+            message => message,
+        })
+    }
+}
+
+/// Replaces results introduced after the given protocol version by their closest older equivalent
+///
+/// Aborted games become draws, draws become games called at the turn limit,
+/// resignations become forfeits and forfeits become wins.
+fn downgrade_result(result: GameResult, protocol_version: u32) -> GameResult {
+    match result {
+        GameResult::Aborted {
+            total_turns,
+            scores,
+        } if protocol_version < 5 => downgrade_result(
+            GameResult::Draw {
+                total_turns,
+                scores,
+            },
+            protocol_version,
+        ),
+        GameResult::Draw {
+            total_turns,
+            scores,
+        } if protocol_version < 3 => GameResult::MaxTurns {
+            total_turns,
+            scores,
+        },
+        GameResult::Resigned {
+            winner,
+            total_turns,
+            scores,
+        } if protocol_version < 3 => downgrade_result(
+            GameResult::Forfeit {
+                winner,
+                total_turns,
+                scores,
+            },
+            protocol_version,
+        ),
+        GameResult::Forfeit {
+            winner,
+            total_turns,
+            scores,
+        } if protocol_version < 2 => GameResult::Finished {
+            winner,
+            total_turns,
+            scores,
+        },
+        result => result,
+    }
 }
 
 /// Messages sent from a Remote Client to the Server
@@ -74,41 +368,234 @@ pub enum RemoteInMessage {
     /// Hello - Request new session
     ///
     /// Sent by a new client to initiate a connection.
-    Hello,
+    /// All fields are optional so that clients of the original protocol keep working.
+    Hello {
+        /// Highest protocol version spoken by the client
+        #[serde(default = "legacy_protocol_version")]
+        protocol_version: u32,
+        /// Name of the client, for logging and display purposes
+        #[serde(default)]
+        client_name: Option<String>,
+        /// Optional features requested by the client
+        #[serde(default)]
+        capabilities: Vec<Capability>,
+    },
     /// Reconnect - Request resume session
     ///
     /// Sent by a client trying to resume a previous session.
-    Reconnect { session_id: Uuid },
+    /// The protocol is negotiated again, as in `Hello`.
+    Reconnect {
+        session_id: Uuid,
+        /// Highest protocol version spoken by the client
+        #[serde(default = "legacy_protocol_version")]
+        protocol_version: u32,
+        /// Optional features requested by the client
+        #[serde(default)]
+        capabilities: Vec<Capability>,
+    },
     /// Movement made by player (index based)
     ///
     /// Sent when the user selects a move. The index corresponds to the list
     /// of valid moves sent in the `Turn` message.
     Choice { movement_index: usize },
+    /// Movement made by player (coordinate based)
+    ///
+    /// Moves the piece at `from` to `to`, either by a single step or by hopping.
+    /// Must match one of the movements available in the current turn.
+    Move { from: HexIdx, to: HexIdx },
+    /// Movement made by player (hopping path)
+    ///
+    /// Moves the piece at the first index of the path through every hop of the path.
+    MovePath { path: Vec<HexIdx> },
+    /// Resign the game
+    Resign,
+    /// Offer a draw to the opponent
+    OfferDraw,
+    /// Accept the draw offered by the opponent
+    AcceptDraw,
+    /// Decline the draw offered by the opponent
+    DeclineDraw,
+    /// Ask the opponent to take back the player's last movement
+    RequestTakeback,
+    /// Accept the takeback requested by the opponent
+    AcceptTakeback,
+    /// Decline the takeback requested by the opponent
+    DeclineTakeback,
+    /// Chat message to the room
+    ///
+    /// Limited to [`CHAT_MESSAGE_LENGTH`] bytes.
+    Chat { text: String },
+    /// Ask for a rematch, with the seats swapped, once the game finished
+    Rematch,
+    /// Leave the seat once the game finished, closing the session
+    ReturnToLobby,
+}
+
+/// Wire encoding of remote messages
+///
+/// CBOR is the default encoding. JSON is meant for debugging and for clients
+/// without a CBOR library, e.g. `websocat` or a browser console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// Compact binary encoding
+    #[default]
+    Cbor,
+    /// Human readable encoding
+    Json,
+}
+
+impl Encoding {
+    /// Every supported encoding, in order of preference
+    pub const fn variants() -> [Encoding; 2] {
+        [Encoding::Cbor, Encoding::Json]
+    }
+
+    /// Name of the WebSocket subprotocol selecting the encoding
+    pub const fn subprotocol(&self) -> &'static str {
+        match self {
+            Encoding::Cbor => "sternhalma.cbor",
+            Encoding::Json => "sternhalma.json",
+        }
+    }
+
+    /// Finds the encoding selected by a WebSocket subprotocol
+    pub fn from_subprotocol(subprotocol: &str) -> Option<Self> {
+        Encoding::variants()
+            .into_iter()
+            .find(|encoding| encoding.subprotocol() == subprotocol)
+    }
+
+    /// Guesses the encoding of a byte stream from its first byte
+    ///
+    /// JSON messages are objects, possibly preceded by whitespace, while CBOR frames
+    /// start with a big-endian length that never begins with those characters
+    /// within [`REMOTE_MESSAGE_LENGTH`].
+    pub fn detect(first_byte: u8) -> Self {
+        if first_byte == b'{' || first_byte.is_ascii_whitespace() {
+            Encoding::Json
+        } else {
+            Encoding::Cbor
+        }
+    }
+
+    /// Serializes a message
+    pub fn to_bytes<T: Serialize>(&self, message: &T) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self {
+            Encoding::Cbor => ciborium::into_writer(message, &mut buf)
+                .context("Failed to serialize remote message")?,
+            Encoding::Json => serde_json::to_writer(&mut buf, message)
+                .context("Failed to serialize remote message")?,
+        }
+        Ok(buf)
+    }
+
+    /// Deserializes a message
+    pub fn from_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        match self {
+            Encoding::Cbor => {
+                ciborium::from_reader(bytes).context("Failed to deserialize remote message")
+            }
+            Encoding::Json => {
+                serde_json::from_slice(bytes).context("Failed to deserialize remote message")
+            }
+        }
+    }
 }
 
 impl RemoteInMessage {
     /// deserializes a `RemoteInMessage` from a byte slice using `ciborium` (CBOR).
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        ciborium::from_reader(bytes).with_context(|| "Failed to deserialize remote message")
+        Encoding::Cbor.from_bytes(bytes)
     }
 }
 
 // Codecs
 
+/// Framing of messages on a byte stream
+///
+/// CBOR messages are length-delimited, JSON messages are newline-delimited.
+#[derive(Debug)]
+enum Framing {
+    Cbor(LengthDelimitedCodec),
+    Json(LinesCodec),
+}
+
+impl Framing {
+    fn new(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Cbor => Framing::Cbor(LengthDelimitedCodec::new()),
+            Encoding::Json => Framing::Json(LinesCodec::new_with_max_length(REMOTE_MESSAGE_LENGTH)),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        match self {
+            Framing::Cbor(delegate) => {
+                // Decode the frame first
+                let bytes = match delegate.decode(src)? {
+                    Some(bytes) => bytes,
+                    None => return Ok(None),
+                };
+
+                // Deserialize the payload
+                Encoding::Cbor.from_bytes(&bytes).map(Some)
+            }
+            Framing::Json(delegate) => {
+                let line = match delegate.decode(src).context("Failed to frame message")? {
+                    Some(line) => line,
+                    None => return Ok(None),
+                };
+
+                Encoding::Json.from_bytes(line.as_bytes()).map(Some)
+            }
+        }
+    }
+
+    fn encode<T: Serialize>(&mut self, item: &T, dst: &mut BytesMut) -> Result<()> {
+        match self {
+            Framing::Cbor(delegate) => {
+                // Serialize the payload
+                let buf = Encoding::Cbor.to_bytes(item)?;
+
+                // Frame the payload
+                delegate
+                    .encode(Bytes::from(buf), dst)
+                    .context("Failed to frame message")
+            }
+            Framing::Json(delegate) => {
+                let line =
+                    serde_json::to_string(item).context("Failed to serialize remote message")?;
+                delegate
+                    .encode(line, dst)
+                    .context("Failed to frame message")
+            }
+        }
+    }
+}
+
 /// Server-side Codec
 ///
-/// Handles framing (length-delimited) and serialization/deserialization for the server.
+/// Handles framing and serialization/deserialization for the server.
 /// Decodes `RemoteInMessage` and Encodes `RemoteOutMessage`.
+///
+/// The encoding is detected from the first byte sent by the client,
+/// so that both CBOR and JSON clients can connect to the same listener.
 #[derive(Debug)]
 pub struct ServerCodec {
-    /// Underlying framing codec
-    delegate: LengthDelimitedCodec,
+    /// Underlying framing codec, once the encoding is known
+    framing: Option<Framing>,
 }
 
 impl ServerCodec {
     pub fn new() -> Self {
+        Self { framing: None }
+    }
+
+    /// Creates a codec for a fixed encoding
+    pub fn with_encoding(encoding: Encoding) -> Self {
         Self {
-            delegate: LengthDelimitedCodec::new(),
+            framing: Some(Framing::new(encoding)),
         }
     }
 }
@@ -124,16 +611,16 @@ impl Decoder for ServerCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // Decode the frame first
-        let bytes = match self.delegate.decode(src)? {
-            Some(bytes) => bytes,
-            None => return Ok(None),
+        let framing = match (&mut self.framing, src.first()) {
+            (Some(framing), _) => framing,
+            (None, None) => return Ok(None),
+            (framing @ None, Some(&first_byte)) => {
+                let encoding = Encoding::detect(first_byte);
+                log::debug!("Detected {encoding:?} encoding");
+                framing.insert(Framing::new(encoding))
+            }
         };
-
-        // Deserialize the payload
-        ciborium::from_reader(std::io::Cursor::new(bytes))
-            .map(Some)
-            .context("Failed to deserialize remote message")
+        framing.decode(src)
     }
 }
 
@@ -141,30 +628,30 @@ impl Encoder<RemoteOutMessage> for ServerCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RemoteOutMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        // Serialize the payload
-        let mut buf = Vec::new();
-        ciborium::into_writer(&item, &mut buf).context("Failed to serialize remote message")?;
-
-        // Frame the payload
-        self.delegate
-            .encode(Bytes::from(buf), dst)
-            .context("Failed to frame message")
+        self.framing
+            .get_or_insert_with(|| Framing::new(Encoding::default()))
+            .encode(&item, dst)
     }
 }
 
 /// Client-side Codec
 ///
-/// Handles framing (length-delimited) and serialization/deserialization for the client (used in tests/bots).
+/// Handles framing and serialization/deserialization for the client (used in tests/bots).
 /// Decodes `RemoteOutMessage` and Encodes `RemoteInMessage`.
 #[derive(Debug)]
 pub struct ClientCodec {
-    delegate: LengthDelimitedCodec,
+    framing: Framing,
 }
 
 impl ClientCodec {
     pub fn new() -> Self {
+        Self::with_encoding(Encoding::default())
+    }
+
+    /// Creates a codec for the given encoding
+    pub fn with_encoding(encoding: Encoding) -> Self {
         Self {
-            delegate: LengthDelimitedCodec::new(),
+            framing: Framing::new(encoding),
         }
     }
 }
@@ -180,14 +667,7 @@ impl Decoder for ClientCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let bytes = match self.delegate.decode(src)? {
-            Some(bytes) => bytes,
-            None => return Ok(None),
-        };
-
-        ciborium::from_reader(std::io::Cursor::new(bytes))
-            .map(Some)
-            .context("Failed to deserialize remote message")
+        self.framing.decode(src)
     }
 }
 
@@ -195,10 +675,6 @@ impl Encoder<RemoteInMessage> for ClientCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RemoteInMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let mut buf = Vec::new();
-        ciborium::into_writer(&item, &mut buf).context("Failed to serialize remote message")?;
-        self.delegate
-            .encode(Bytes::from(buf), dst)
-            .context("Failed to frame message")
+        self.framing.encode(&item, dst)
     }
 }
@@ -0,0 +1,462 @@
+//! # Rooms Module
+//!
+//! This module keeps track of the game rooms hosted by the server.
+//! Every room runs its own [`Server`] task, with its own channels and configuration,
+//! and publishes the state of its game for the REST API and the live feed.
+//! When a journal directory is set, every room records its game there and unfinished games
+//! can be recovered after a restart. When an archive is set, finished games are archived.
+//! Rooms are unregistered once their server task ends, and the number of rooms created on demand can be limited.
+//! Spectators of a room chat on a channel of their own, apart from the players.
+//!
+//! ## Key Components
+//! - [`Rooms`]: Registry of the rooms hosted by the server.
+//! - [`Room`]: Handle to a single room.
+//! - [`GameSnapshot`]: State of the game played in a room.
+//! - [`SpectatorChat`]: Chat of the spectators of a room.
+//! - [`RoomsError`]: Reasons for refusing to create a room.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    GameResult, Scores,
+    board::{HexIdx, movement::MovementIndices, player::Player},
+};
+
+use super::{
+    CHAT_RATE_LIMIT, CHAT_RATE_WINDOW, Server, ServerConfig,
+    archive::Archive,
+    handshake::AppState,
+    journal::{self, Journal, JournalRecord},
+    messages::{ClientMessage, ServerBroadcast},
+    now_millis,
+    protocol::CHAT_MESSAGE_LENGTH,
+};
+
+/// Capacity of the channels between the tasks of a room
+pub const LOCAL_CHANNEL_CAPACITY: usize = 32;
+
+/// Phase of the game played in a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomStatus {
+    /// Waiting for players to connect
+    #[default]
+    Waiting,
+    /// Game in progress
+    Playing,
+    /// Game finished with a result
+    Finished,
+    /// Room closed before the game finished (e.g. players did not connect in time)
+    Closed,
+}
+
+/// Seat of a room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Seat {
+    /// Player assigned to the seat
+    pub player: Player,
+    /// Name announced by the client in its `Hello`
+    pub name: Option<String>,
+    /// Whether a client is currently connected to the seat
+    pub connected: bool,
+}
+
+/// State of the game played in a room
+///
+/// Published by the [`Server`] every time the game changes. Coordinates and players are absolute.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GameSnapshot {
+    /// Identifier of the game, which changes with every game of a persistent room
+    pub game_id: Uuid,
+    /// Phase of the game
+    pub status: RoomStatus,
+    /// Seats taken by players
+    pub seats: Vec<Seat>,
+    /// Pieces on the board
+    pub board: Vec<(HexIdx, Player)>,
+    /// Player to move, if the game is still ongoing
+    pub to_move: Option<Player>,
+    /// Number of turns played
+    pub turn: usize,
+    /// Current scores
+    pub scores: Scores,
+    /// Movements played so far along with the player who made them
+    pub history: Vec<(Player, MovementIndices)>,
+    /// Result of the game, once finished
+    pub result: Option<GameResult>,
+    /// Time the game started, in milliseconds since the Unix epoch
+    ///
+    /// Unknown for games recovered from their journal.
+    pub started_at: Option<u64>,
+    /// Time the game finished, in milliseconds since the Unix epoch
+    pub finished_at: Option<u64>,
+}
+
+/// Handle to a room hosted by the server
+#[derive(Clone)]
+pub struct Room {
+    /// Channels used by connections to join the room
+    pub app_state: AppState,
+    /// Configuration of the room
+    pub config: ServerConfig,
+    /// Creation time of the room, in milliseconds since the Unix epoch
+    pub created_at: u64,
+    /// Latest state of the game played in the room
+    pub snapshot: watch::Receiver<GameSnapshot>,
+    /// Chat of the spectators following the room
+    pub spectator_chat: SpectatorChat,
+}
+
+/// Chat message sent by a spectator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpectatorMessage {
+    /// Content of the message
+    pub text: String,
+    /// Time the message was received, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Reasons for refusing a chat message from a spectator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SpectatorChatError {
+    /// Chat is muted in the room
+    #[error("chat is muted in this room")]
+    Muted,
+    /// The message is empty or longer than [`CHAT_MESSAGE_LENGTH`]
+    #[error("chat message is empty or too long")]
+    Invalid,
+    /// The spectators sent too many messages in a short time
+    #[error("too many chat messages")]
+    RateLimited,
+}
+
+/// Chat of the spectators of a room
+///
+/// Messages are broadcast to the spectators only, on a channel that the room's [`Server`] does not know of,
+/// so that they never reach the players. Spectators are anonymous, and share the rate limit of a single player.
+#[derive(Debug, Clone)]
+pub struct SpectatorChat {
+    /// Channel of the messages, subscribed to by the live feeds of the room
+    tx: broadcast::Sender<SpectatorMessage>,
+    /// Time of the recent messages, for rate limiting
+    times: Arc<Mutex<VecDeque<Instant>>>,
+    /// Whether chat is muted in the room
+    muted: bool,
+}
+
+impl SpectatorChat {
+    fn new(muted: bool) -> Self {
+        Self {
+            tx: broadcast::channel(LOCAL_CHANNEL_CAPACITY).0,
+            times: Arc::default(),
+            muted,
+        }
+    }
+
+    /// Broadcasts a message to the spectators
+    pub fn send(&self, text: String) -> Result<SpectatorMessage, SpectatorChatError> {
+        if self.muted {
+            return Err(SpectatorChatError::Muted);
+        }
+        if text.trim().is_empty() || text.len() > CHAT_MESSAGE_LENGTH {
+            return Err(SpectatorChatError::Invalid);
+        }
+
+        // Forget messages that left the rate limiting window
+        let now = Instant::now();
+        let mut times = self.times.lock().expect("Spectator chat lock poisoned");
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= CHAT_RATE_WINDOW)
+        {
+            times.pop_front();
+        }
+        if times.len() >= CHAT_RATE_LIMIT {
+            return Err(SpectatorChatError::RateLimited);
+        }
+        times.push_back(now);
+
+        let message = SpectatorMessage {
+            text,
+            timestamp: now_millis(),
+        };
+        // Nobody may be following the room
+        let _ = self.tx.send(message.clone());
+        Ok(message)
+    }
+
+    /// Subscribes to the messages sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SpectatorMessage> {
+        self.tx.subscribe()
+    }
+}
+
+/// Reasons for refusing to create a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum RoomsError {
+    /// The server already hosts as many rooms as allowed
+    #[error("at most {0} rooms can be hosted at once")]
+    Full(usize),
+    /// The server is shutting down
+    #[error("rooms are shut down")]
+    ShutDown,
+}
+
+/// Registry of the rooms hosted by the server
+///
+/// Cheap to clone, every clone refers to the same registry.
+/// Rooms are unregistered once their server task ends, after which their finished games
+/// are only found in the archive, if enabled.
+#[derive(Clone, Default)]
+pub struct Rooms {
+    rooms: Arc<RwLock<HashMap<Uuid, Room>>>,
+    /// Maximum number of rooms hosted at once, beyond which no room is created on demand
+    max_rooms: Option<usize>,
+    /// Lock held while creating a room on demand, so that concurrent creations respect the maximum
+    creating: Arc<Mutex<()>>,
+    /// Directory where the games are journaled, if enabled
+    journal_dir: Option<Arc<PathBuf>>,
+    /// Archive of finished games, if enabled
+    archive: Option<Arc<Archive>>,
+    /// Signal shutting down every room
+    shutdown: CancellationToken,
+    /// Server, client and bot tasks of the rooms
+    tasks: TaskTracker,
+}
+
+impl Rooms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Journals the games of the rooms in the given directory
+    pub fn with_journal_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.journal_dir = Some(Arc::new(dir.into()));
+        self
+    }
+
+    /// Archives the finished games of the rooms
+    pub fn with_archive(mut self, archive: Archive) -> Self {
+        self.archive = Some(Arc::new(archive));
+        self
+    }
+
+    /// Limits the number of rooms hosted at once
+    ///
+    /// The default room and the recovered ones count towards the limit, but are never refused.
+    pub fn with_max_rooms(mut self, max_rooms: usize) -> Self {
+        self.max_rooms = Some(max_rooms);
+        self
+    }
+
+    /// Creates a room and spawns its server task
+    ///
+    /// The room closes once nobody holds a seat in its lobby for the connection timeout,
+    /// even if it is persistent.
+    /// Returns the identifier of the room along with the handle of its server task,
+    /// or [`RoomsError::Full`] if the server already hosts as many rooms as allowed.
+    pub fn create(&self, config: ServerConfig) -> Result<(Uuid, JoinHandle<()>)> {
+        let _creating = self.creating.lock().expect("Rooms lock poisoned");
+        if let Some(max_rooms) = self.max_rooms
+            && self.rooms.read().expect("Rooms lock poisoned").len() >= max_rooms
+        {
+            return Err(RoomsError::Full(max_rooms).into());
+        }
+        self.create_room(config, false)
+    }
+
+    /// Creates the room joined by connections that do not name one
+    pub fn create_default(&self, config: ServerConfig) -> Result<(Uuid, JoinHandle<()>)> {
+        self.create_room(config, true)
+    }
+
+    /// Creates a room with a new game, journaled if enabled
+    fn create_room(&self, config: ServerConfig, default: bool) -> Result<(Uuid, JoinHandle<()>)> {
+        let id = Uuid::new_v4();
+        let created_at = now_millis();
+        let header = JournalRecord::header(id, created_at, default, &config);
+        self.spawn(id, created_at, config, default, |server| {
+            match &self.journal_dir {
+                Some(dir) => Ok(server.with_journal(Journal::create(dir, header)?)),
+                None => Ok(server),
+            }
+        })
+    }
+
+    /// Restores the unfinished games of the journal directory and spawns their rooms
+    ///
+    /// Returns the recovered default room, if any, along with the handle of its server task.
+    /// Other recovered rooms run until their game ends.
+    pub fn recover(&self) -> Result<Option<(Uuid, JoinHandle<()>)>> {
+        let Some(dir) = &self.journal_dir else {
+            return Ok(None);
+        };
+
+        let mut default_room = None;
+        for recovered in journal::recover(dir)? {
+            let id = recovered.id;
+            let default = recovered.default;
+            let (id, handle) = self
+                .spawn(
+                    id,
+                    recovered.created_at,
+                    recovered.config,
+                    default,
+                    |server| {
+                        Ok(server.with_journal(recovered.journal).with_recovered_game(
+                            recovered.game_id,
+                            recovered.game,
+                            recovered.sessions,
+                        ))
+                    },
+                )
+                .with_context(|| format!("Failed to restore room {id}"))?;
+            // The most recent default room is kept, in case several were left behind
+            if default {
+                default_room = Some((id, handle));
+            }
+        }
+        Ok(default_room)
+    }
+
+    /// Creates the server of a room, registers the room and spawns its server task
+    ///
+    /// The server is set up by the given function before it starts.
+    /// Rooms other than the default one close once their lobby is left empty.
+    /// The room is unregistered once its server task ends.
+    fn spawn(
+        &self,
+        id: Uuid,
+        created_at: u64,
+        config: ServerConfig,
+        default: bool,
+        setup: impl FnOnce(Server) -> Result<Server>,
+    ) -> Result<(Uuid, JoinHandle<()>)> {
+        if self.shutdown.is_cancelled() {
+            return Err(RoomsError::ShutDown.into());
+        }
+
+        // Client threads -> Server thread
+        let (client_msg_tx, client_msg_rx) = mpsc::channel::<ClientMessage>(LOCAL_CHANNEL_CAPACITY);
+        // Server thread -> Client threads
+        let (server_broadcast_tx, _) =
+            broadcast::channel::<ServerBroadcast>(LOCAL_CHANNEL_CAPACITY);
+        // Connection handlers -> Server thread
+        let (main_tx, main_rx) = mpsc::channel(LOCAL_CHANNEL_CAPACITY);
+
+        let server = Server::new(
+            id,
+            created_at,
+            main_rx,
+            client_msg_rx,
+            client_msg_tx.clone(),
+            server_broadcast_tx.clone(),
+            config.clone(),
+        )
+        .map(|server| {
+            server
+                .with_shutdown(self.shutdown.child_token())
+                .with_tasks(self.tasks.clone())
+        })
+        .map(|server| {
+            if default {
+                server
+            } else {
+                server.with_idle_timeout(config.connection_timeout)
+            }
+        })
+        .map(|server| match &self.archive {
+            Some(archive) => server.with_archive(archive.clone()),
+            None => server,
+        })
+        .and_then(setup)
+        .with_context(|| "Failed to create server")?;
+
+        let room = Room {
+            app_state: AppState {
+                main_tx,
+                client_msg_tx,
+                server_broadcast_tx,
+                tasks: self.tasks.clone(),
+            },
+            spectator_chat: SpectatorChat::new(config.mute_chat),
+            config,
+            created_at,
+            snapshot: server.snapshots(),
+        };
+        self.rooms
+            .write()
+            .expect("Rooms lock poisoned")
+            .insert(id, room.clone());
+        log::info!("Created room {id}");
+
+        let rooms = self.rooms.clone();
+        let handle = self.tasks.spawn(async move {
+            if let Err(e) = server.try_run().await {
+                log::error!("Server of room {id} encountered an error: {e:?}");
+            }
+            rooms.write().expect("Rooms lock poisoned").remove(&id);
+            log::info!("Removed room {id}");
+        });
+
+        Ok((id, handle))
+    }
+
+    /// Shuts down every room
+    ///
+    /// Games in progress are aborted, or left to be resumed if journaled, and their players disconnected.
+    /// No room can be created afterwards.
+    pub fn shutdown(&self) {
+        self.tasks.close();
+        self.shutdown.cancel();
+    }
+
+    /// Signal cancelled once the rooms are shut down
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Waits for the server, client and bot tasks of every room to finish, once shut down
+    ///
+    /// Clients finish once they told their remote client why it is disconnected.
+    pub async fn wait(&self) {
+        self.tasks.wait().await;
+    }
+
+    /// Finds a room by its identifier
+    pub fn get(&self, id: &Uuid) -> Option<Room> {
+        self.rooms
+            .read()
+            .expect("Rooms lock poisoned")
+            .get(id)
+            .cloned()
+    }
+
+    /// Lists every room along with its identifier, oldest first
+    pub fn list(&self) -> Vec<(Uuid, Room)> {
+        let mut rooms: Vec<_> = self
+            .rooms
+            .read()
+            .expect("Rooms lock poisoned")
+            .iter()
+            .map(|(id, room)| (*id, room.clone()))
+            .collect();
+        rooms.sort_by_key(|(_, room)| room.created_at);
+        rooms
+    }
+}
@@ -0,0 +1,274 @@
+//! # Server-Sent Events Module
+//!
+//! This module provides a read-only live feed of a room's game over Server-Sent Events,
+//! for consumers that only follow games (browser overlays, log shippers) and do not take a seat.
+//!
+//! The feed follows the state published by the room's [`Server`](super::Server): the movements
+//! already played are replayed first, then new ones are streamed as they happen.
+//! Coordinates and players are absolute.
+//! In persistent rooms, the feed goes on with the next game of the room, announced by a `new_game` event.
+//!
+//! ## Events
+//! - `movement`: A movement was played. The event id is the turn number of the movement within its game.
+//! - `taken_back`: Movements were taken back after a takeback was accepted.
+//! - `game_finished`: The game reached a result. The stream ends afterwards, unless the room is persistent.
+//! - `new_game`: A persistent room started its next game, on a fresh board.
+//! - `chat`: A spectator sent a chat message. Messages are not replayed, and players never receive them.
+
+use std::{collections::VecDeque, convert::Infallible};
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures::{Stream, stream};
+use serde::Serialize;
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    Game, GameResult, Scores,
+    board::{movement::MovementIndices, player::Player},
+};
+
+use super::{
+    http::{HttpState, room_not_found},
+    rooms::{GameSnapshot, RoomStatus, SpectatorMessage},
+};
+
+/// Event of the live feed
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum FeedEvent {
+    /// `movement` event
+    Movement {
+        /// Turn number of the movement, starting at 1
+        turn: usize,
+        /// The player who made the movement
+        player: Player,
+        /// The movement that was performed
+        movement: MovementIndices,
+        /// The scores after the movement
+        scores: Scores,
+    },
+    /// `taken_back` event
+    TakenBack {
+        /// Number of turns played once the movements are undone
+        turn: usize,
+        /// The undone movements along with the players who made them, most recent first
+        movements: Vec<(Player, MovementIndices)>,
+        /// The scores after undoing the movements
+        scores: Scores,
+    },
+    /// `game_finished` event
+    GameFinished {
+        /// The result of the game
+        result: GameResult,
+    },
+    /// `new_game` event
+    NewGame {
+        /// Identifier of the new game
+        id: Uuid,
+    },
+    /// `chat` event
+    Chat(SpectatorMessage),
+}
+
+impl FeedEvent {
+    /// Converts the feed event into a Server-Sent Event
+    fn to_event(&self) -> Event {
+        let (name, id) = match self {
+            FeedEvent::Movement { turn, .. } => ("movement", Some(*turn)),
+            FeedEvent::TakenBack { .. } => ("taken_back", None),
+            FeedEvent::GameFinished { .. } => ("game_finished", None),
+            FeedEvent::NewGame { .. } => ("new_game", None),
+            FeedEvent::Chat(_) => ("chat", None),
+        };
+        let event = Event::default().event(name);
+        let event = match id {
+            Some(id) => event.id(id.to_string()),
+            None => event,
+        };
+        event.json_data(self).unwrap_or_else(|e| {
+            log::error!("Failed to serialize feed event: {e:?}");
+            Event::default().comment("serialization error")
+        })
+    }
+}
+
+/// Follows the snapshots of a room and turns their changes into feed events
+struct Feed {
+    /// Snapshots published by the room's server
+    snapshots: watch::Receiver<GameSnapshot>,
+    /// Chat messages of the spectators
+    chat: broadcast::Receiver<SpectatorMessage>,
+    /// Identifier of the game described by the events sent so far, once known
+    game_id: Option<Uuid>,
+    /// Replica of the game as described by the events sent so far
+    game: Game,
+    /// Events waiting to be sent
+    pending: VecDeque<FeedEvent>,
+    /// Movement events up to this turn are not sent (`Last-Event-ID` of a reconnecting client)
+    skip_until: usize,
+    /// Whether the result of the game was sent
+    finished: bool,
+    /// Whether the room goes on with another game once one is over
+    persistent: bool,
+    /// Whether the feed ends once pending events are sent
+    done: bool,
+}
+
+impl Feed {
+    fn new(
+        snapshots: watch::Receiver<GameSnapshot>,
+        chat: broadcast::Receiver<SpectatorMessage>,
+        skip_until: usize,
+        persistent: bool,
+    ) -> Self {
+        Self {
+            snapshots,
+            chat,
+            game_id: None,
+            game: Game::new(),
+            pending: VecDeque::new(),
+            skip_until,
+            finished: false,
+            persistent,
+            done: false,
+        }
+    }
+
+    /// Queues the events leading from the replica to the given snapshot
+    fn update(&mut self, snapshot: &GameSnapshot) {
+        // The room moved on to its next game, which starts from the initial position
+        if self
+            .game_id
+            .is_some_and(|game_id| game_id != snapshot.game_id)
+        {
+            self.game = Game::new();
+            self.skip_until = 0;
+            self.finished = false;
+            self.pending.push_back(FeedEvent::NewGame {
+                id: snapshot.game_id,
+            });
+        }
+        self.game_id = Some(snapshot.game_id);
+
+        // Undo the movements that are no longer part of the history
+        let common = self
+            .game
+            .history()
+            .iter()
+            .zip(snapshot.history.iter())
+            .take_while(|(played, (_, movement))| *played == movement)
+            .count();
+        let mut undone = Vec::new();
+        while self.game.history().len() > common {
+            undone.extend(self.game.undo_movement());
+        }
+        if !undone.is_empty() {
+            let turn = self.game.history().len();
+            self.skip_until = self.skip_until.min(turn);
+            self.pending.push_back(FeedEvent::TakenBack {
+                turn,
+                movements: undone,
+                scores: self.game.status().scores(),
+            });
+        }
+
+        // Play the new movements
+        for (player, movement) in &snapshot.history[common..] {
+            // Movements in the snapshot were validated by the server
+            unsafe {
+                self.game.apply_movement_unchecked(movement);
+            }
+            let turn = self.game.history().len();
+            if turn > self.skip_until {
+                self.pending.push_back(FeedEvent::Movement {
+                    turn,
+                    player: *player,
+                    movement: *movement,
+                    scores: self.game.status().scores(),
+                });
+            }
+        }
+
+        match (&snapshot.result, snapshot.status) {
+            (Some(_), _) if self.finished => {}
+            (Some(result), _) => {
+                self.pending.push_back(FeedEvent::GameFinished {
+                    result: result.clone(),
+                });
+                self.finished = true;
+                self.done = !self.persistent;
+            }
+            (None, RoomStatus::Closed) => self.done = true,
+            _ => {}
+        }
+    }
+
+    /// Waits for the next feed event
+    ///
+    /// Returns `None` once the game is over in a room that is not persistent, or when the room's server is gone.
+    async fn next(&mut self) -> Option<FeedEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if self.done {
+                return None;
+            }
+            // The snapshot is marked as seen so that only later changes wake the feed
+            let snapshot = self.snapshots.borrow_and_update().clone();
+            self.update(&snapshot);
+            if self.pending.is_empty() && !self.done {
+                tokio::select! {
+                    changed = self.snapshots.changed() => changed.ok()?,
+                    message = self.chat.recv() => match message {
+                        Ok(message) => self.pending.push_back(FeedEvent::Chat(message)),
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            log::debug!("Live feed missed {n} chat messages");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    },
+                }
+            }
+        }
+    }
+
+    /// Converts the feed into a stream of Server-Sent Events
+    fn into_stream(self) -> impl Stream<Item = Result<Event, Infallible>> {
+        stream::unfold(self, |mut feed| async move {
+            let event = feed.next().await?;
+            Some((Ok(event.to_event()), feed))
+        })
+    }
+}
+
+/// Axum handler for the live feed of a room
+///
+/// Clients reconnecting with a `Last-Event-ID` header resume after the given turn of the current game.
+pub async fn events_handler(
+    State(state): State<HttpState>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(room) = state.rooms.get(&id) else {
+        return room_not_found(id);
+    };
+    let skip_until = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+
+    let chat = room.spectator_chat.subscribe();
+    let feed = Feed::new(room.snapshot, chat, skip_until, room.config.persistent);
+    Sse::new(feed.into_stream())
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
@@ -0,0 +1,479 @@
+//! # Telnet Module
+//!
+//! This module implements a human-readable line protocol, playable with `nc` or `telnet`.
+//!
+//! The adapter translates between plain text lines and the messages of the raw protocol, and is handed to
+//! the handshake as any other connection. The first line sent by the player is the name announced in `Hello`,
+//! or `reconnect <SESSION>` to resume a session. The board is then printed with its [`Display`](std::fmt::Display)
+//! implementation every time it changes, and the available movements are numbered on every turn.
+//!
+//! Like every client, players see the board from the side of `Player1`.
+//!
+//! ## Key Components
+//! - [`connection`]: Adapter turning a text connection into a client connection.
+//! - [`Command`]: Command typed by a player.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow};
+use futures::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::{
+    codec::{FramedRead, LinesCodec},
+    sync::PollSender,
+};
+use uuid::Uuid;
+
+use crate::sternhalma::{
+    GameResult,
+    board::{Board, HexIdx, movement::MovementIndices, player::Player},
+};
+
+use super::{
+    client::{ClientSink, ClientStream},
+    protocol::{Capability, PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage},
+};
+
+/// Maximum length of a line sent by a player
+const MAX_LINE_LENGTH: usize = 1024;
+/// Capacity of the channels between the adapter and the client task
+const CHANNEL_CAPACITY: usize = 32;
+
+/// Commands available once the game has started
+const HELP: &str = "\
+Commands:
+  <N>                 Play movement number N of the list
+  move Q,R Q,R        Play the movement from a cell to another
+  board               Print the board
+  resign              Resign the game
+  draw                Offer a draw, or accept the draw offered by your opponent
+  decline             Decline the draw or takeback offered by your opponent
+  takeback            Ask to take back your last movement, or accept the takeback asked by your opponent
+  say <TEXT>          Send a chat message
+  rematch             Ask for a rematch once the game is over, or accept the rematch asked by your opponent
+  lobby               Leave your seat once the game is over
+  quit                Leave the game
+  help                Print this help";
+
+/// Command typed by a player
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Play a movement of the list printed on the last turn, numbered from 1
+    Choice(usize),
+    /// Play a movement by coordinates
+    Move {
+        from: HexIdx,
+        to: HexIdx,
+    },
+    Board,
+    Resign,
+    Draw,
+    Decline,
+    Takeback,
+    Say(String),
+    Rematch,
+    Lobby,
+    Quit,
+    Help,
+}
+
+/// Parses a cell written as `Q,R`
+fn parse_cell(cell: &str) -> Option<HexIdx> {
+    let (q, r) = cell.split_once(',')?;
+    Some([q.trim().parse().ok()?, r.trim().parse().ok()?])
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let line = line.trim();
+        if let Ok(number) = line.parse::<usize>() {
+            return Ok(Command::Choice(number));
+        }
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command.to_lowercase().as_str() {
+            "move" | "m" => {
+                let mut cells = rest.split_whitespace().map(parse_cell);
+                match (cells.next(), cells.next(), cells.next()) {
+                    (Some(Some(from)), Some(Some(to)), None) => Ok(Command::Move { from, to }),
+                    _ => Err(anyhow!("Usage: move Q,R Q,R")),
+                }
+            }
+            "board" | "b" => Ok(Command::Board),
+            "resign" => Ok(Command::Resign),
+            "draw" => Ok(Command::Draw),
+            "decline" => Ok(Command::Decline),
+            "takeback" => Ok(Command::Takeback),
+            "say" if !rest.trim().is_empty() => Ok(Command::Say(rest.trim().to_string())),
+            "say" => Err(anyhow!("Usage: say <TEXT>")),
+            "rematch" => Ok(Command::Rematch),
+            "lobby" => Ok(Command::Lobby),
+            "quit" | "exit" => Ok(Command::Quit),
+            "help" | "?" => Ok(Command::Help),
+            _ => Err(anyhow!(
+                "Unknown command `{line}`, type `help` for the list of commands"
+            )),
+        }
+    }
+}
+
+/// Describes the result of a game from the side of the player
+fn describe_result(result: &GameResult) -> String {
+    let outcome = match result.winner() {
+        Some(Player::Player1) => "You won",
+        Some(Player::Player2) => "You lost",
+        None => "No winner",
+    };
+    format!(
+        "Game over ({kind}) after {turns} turns: {outcome}. Scores: {scores:?}",
+        kind = result.kind(),
+        turns = result.total_turns(),
+        scores = result.scores(),
+    )
+}
+
+/// Writes a movement as `Q,R -> Q,R`
+fn describe_movement([[q1, r1], [q2, r2]]: MovementIndices) -> String {
+    format!("{q1},{r1} -> {q2},{r2}")
+}
+
+/// Turns a connection speaking the line protocol into a client connection
+pub fn connection<R, W>(reader: R, writer: W) -> (ClientStream, ClientSink)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (in_tx, in_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let (out_tx, out_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let session = Session {
+        lines: FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_LINE_LENGTH)),
+        writer,
+        board: Board::empty(),
+        movements: Vec::new(),
+        draw_offered: false,
+        takeback_requested: false,
+    };
+    tokio::spawn(async move {
+        if let Err(e) = session.run(in_tx, out_rx).await {
+            log::debug!("Telnet connection closed: {e:?}");
+        }
+    });
+
+    let stream: ClientStream = Box::pin(ReceiverStream::new(in_rx).map(Ok));
+    let sink: ClientSink = Box::pin(PollSender::new(out_tx).sink_map_err(|e| anyhow!(e)));
+    (stream, sink)
+}
+
+/// Adapter between a player typing commands and its client task
+struct Session<R, W> {
+    /// Lines typed by the player
+    lines: FramedRead<R, LinesCodec>,
+    /// Output to the player
+    writer: W,
+    /// Board as seen by the player
+    board: Board<Player>,
+    /// Movements available on the current turn
+    movements: Vec<MovementIndices>,
+    /// Whether the opponent offered a draw the player did not answer yet
+    draw_offered: bool,
+    /// Whether the opponent asked for a takeback the player did not answer yet
+    takeback_requested: bool,
+}
+
+impl<R, W> Session<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    async fn run(
+        mut self,
+        in_tx: mpsc::Sender<RemoteInMessage>,
+        mut out_rx: mpsc::Receiver<RemoteOutMessage>,
+    ) -> Result<()> {
+        self.print("Welcome to Sternhalma!\nName (or `reconnect <SESSION>`):")
+            .await?;
+        let hello = self.read_hello().await?;
+        in_tx.send(hello).await.with_context(|| "Client closed")?;
+
+        loop {
+            tokio::select! {
+                message = out_rx.recv() => {
+                    let Some(message) = message else { break };
+                    if !self.handle_server_message(message).await? {
+                        break;
+                    }
+                }
+                line = self.lines.next() => {
+                    let Some(line) = line else { break };
+                    let line = line.with_context(|| "Failed to read line")?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match line.parse::<Command>() {
+                        Ok(Command::Quit) => break,
+                        Ok(command) => {
+                            if let Some(message) = self.handle_command(command).await? {
+                                in_tx.send(message).await.with_context(|| "Client closed")?;
+                            }
+                        }
+                        Err(e) => self.print(&e.to_string()).await?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the first line, naming the player or the session to resume
+    async fn read_hello(&mut self) -> Result<RemoteInMessage> {
+        let line = self
+            .lines
+            .next()
+            .await
+            .ok_or(anyhow!("Connection closed"))?
+            .with_context(|| "Failed to read line")?;
+        let capabilities = vec![Capability::CoordinateMoves];
+        let line = line.trim();
+        if let Some(session) = line.strip_prefix("reconnect ") {
+            let session_id = Uuid::parse_str(session.trim()).with_context(|| "Invalid session")?;
+            return Ok(RemoteInMessage::Reconnect {
+                session_id,
+                protocol_version: PROTOCOL_VERSION,
+                capabilities,
+            });
+        }
+        Ok(RemoteInMessage::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: (!line.is_empty()).then(|| line.to_string()),
+            capabilities,
+        })
+    }
+
+    /// Translates a command of the player into a message to the server, if any
+    async fn handle_command(&mut self, command: Command) -> Result<Option<RemoteInMessage>> {
+        let message = match command {
+            Command::Choice(number) => match number.checked_sub(1) {
+                Some(movement_index) if movement_index < self.movements.len() => {
+                    RemoteInMessage::Choice { movement_index }
+                }
+                _ if self.movements.is_empty() => {
+                    self.print("It is not your turn").await?;
+                    return Ok(None);
+                }
+                _ => {
+                    let count = self.movements.len();
+                    self.print(&format!("Choose a movement between 1 and {count}"))
+                        .await?;
+                    return Ok(None);
+                }
+            },
+            Command::Move { from, to } => RemoteInMessage::Move { from, to },
+            Command::Board => {
+                self.print_board().await?;
+                return Ok(None);
+            }
+            Command::Resign => RemoteInMessage::Resign,
+            Command::Draw if std::mem::take(&mut self.draw_offered) => RemoteInMessage::AcceptDraw,
+            Command::Draw => RemoteInMessage::OfferDraw,
+            Command::Decline if std::mem::take(&mut self.draw_offered) => {
+                RemoteInMessage::DeclineDraw
+            }
+            Command::Decline if std::mem::take(&mut self.takeback_requested) => {
+                RemoteInMessage::DeclineTakeback
+            }
+            Command::Decline => {
+                self.print("Nothing to decline").await?;
+                return Ok(None);
+            }
+            Command::Takeback if std::mem::take(&mut self.takeback_requested) => {
+                RemoteInMessage::AcceptTakeback
+            }
+            Command::Takeback => RemoteInMessage::RequestTakeback,
+            Command::Say(text) => RemoteInMessage::Chat { text },
+            Command::Rematch => RemoteInMessage::Rematch,
+            Command::Lobby => RemoteInMessage::ReturnToLobby,
+            Command::Help => {
+                self.print(HELP).await?;
+                return Ok(None);
+            }
+            Command::Quit => return Ok(None),
+        };
+        Ok(Some(message))
+    }
+
+    /// Prints a message of the server to the player
+    ///
+    /// Returns `false` once the connection has to be closed.
+    async fn handle_server_message(&mut self, message: RemoteOutMessage) -> Result<bool> {
+        match message {
+            RemoteOutMessage::Welcome { session_id, .. } => {
+                self.print(&format!(
+                    "Joined with session {session_id}. Waiting for the game to start..."
+                ))
+                .await?;
+            }
+            RemoteOutMessage::Reject { reason } => {
+                self.print(&format!("Rejected: {reason}")).await?;
+                return Ok(false);
+            }
+            RemoteOutMessage::Disconnect { reason } => {
+                let reason = reason.map_or(String::new(), |reason| format!(": {reason}"));
+                self.print(&format!("Disconnected{reason}")).await?;
+                return Ok(false);
+            }
+            RemoteOutMessage::GameStarted {
+                you_move_first,
+                opponents,
+                ..
+            } => {
+                let opponent = opponents
+                    .first()
+                    .and_then(|opponent| opponent.name.clone())
+                    .unwrap_or("an anonymous opponent".to_string());
+                let order = if you_move_first { "first" } else { "second" };
+                self.print(&format!(
+                    "Game started against {opponent}. You play {} and move {order}. Type `help` for the commands.",
+                    Player::Player1
+                ))
+                .await?;
+            }
+            RemoteOutMessage::GameState { board, .. } => {
+                self.board = Board::empty();
+                for (idx, player) in board {
+                    let _ = self.board.set_piece(idx, player);
+                }
+                self.print_board().await?;
+            }
+            RemoteOutMessage::Turn { movements } => {
+                self.movements = movements;
+                let mut text = "Your turn. Movements:".to_string();
+                for (i, movement) in self.movements.iter().enumerate() {
+                    text.push_str(&format!(
+                        "\n  {:>3}) {}",
+                        i + 1,
+                        describe_movement(*movement)
+                    ));
+                }
+                self.print(&text).await?;
+            }
+            RemoteOutMessage::Movement {
+                player,
+                movement,
+                scores,
+            } => {
+                self.movements.clear();
+                self.draw_offered = false;
+                self.takeback_requested = false;
+                self.move_piece(movement[0], movement[1]);
+                self.print_board().await?;
+                self.print(&format!(
+                    "{player} moved {}. Scores: {scores:?}",
+                    describe_movement(movement)
+                ))
+                .await?;
+            }
+            RemoteOutMessage::TakenBack {
+                player, movements, ..
+            } => {
+                self.movements.clear();
+                self.takeback_requested = false;
+                for [from, to] in movements {
+                    self.move_piece(to, from);
+                }
+                self.print_board().await?;
+                self.print(&format!("Movements taken back, {player} to move"))
+                    .await?;
+            }
+            RemoteOutMessage::OpponentDisconnected {
+                player,
+                timeout_secs,
+            } => {
+                self.print(&format!(
+                    "{player} disconnected and has {timeout_secs} seconds to come back"
+                ))
+                .await?;
+            }
+            RemoteOutMessage::OpponentReconnected { player } => {
+                self.print(&format!("{player} reconnected")).await?;
+            }
+            RemoteOutMessage::DrawOffered { player } => {
+                self.draw_offered = true;
+                self.print(&format!(
+                    "{player} offers a draw: type `draw` to accept or `decline`"
+                ))
+                .await?;
+            }
+            RemoteOutMessage::DrawDeclined { player } => {
+                self.print(&format!("{player} declined the draw")).await?;
+            }
+            RemoteOutMessage::TakebackRequested { player } => {
+                self.takeback_requested = true;
+                self.print(&format!(
+                    "{player} asks for a takeback: type `takeback` to accept or `decline`"
+                ))
+                .await?;
+            }
+            RemoteOutMessage::TakebackDeclined { player } => {
+                self.print(&format!("{player} declined the takeback"))
+                    .await?;
+            }
+            RemoteOutMessage::Chat { player, text, .. } => {
+                self.print(&format!("[{player}] {text}")).await?;
+            }
+            RemoteOutMessage::GameFinished { result } => {
+                self.movements.clear();
+                self.print(&describe_result(&result)).await?;
+            }
+            RemoteOutMessage::RematchRequested {
+                player: Player::Player1,
+            } => {
+                self.print("Waiting for your opponent to accept the rematch...")
+                    .await?;
+            }
+            RemoteOutMessage::RematchRequested { player } => {
+                self.print(&format!(
+                    "{player} asks for a rematch: type `rematch` to accept or `lobby` to leave"
+                ))
+                .await?;
+            }
+            RemoteOutMessage::OpponentLeft { player } => {
+                self.print(&format!("{player} left. Waiting for a new opponent..."))
+                    .await?;
+            }
+            RemoteOutMessage::Error { message, .. } => {
+                self.print(&format!("Error: {message}")).await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Moves a piece on the board as seen by the player
+    fn move_piece(&mut self, from: HexIdx, to: HexIdx) {
+        let piece = self.board.get_mut(&from).ok().and_then(Option::take);
+        if let (Some(piece), Ok(cell)) = (piece, self.board.get_mut(&to)) {
+            *cell = Some(piece);
+        }
+    }
+
+    async fn print_board(&mut self) -> Result<()> {
+        let board = self.board.to_string();
+        self.print(board.trim_end()).await
+    }
+
+    /// Writes lines to the player
+    async fn print(&mut self, text: &str) -> Result<()> {
+        self.writer
+            .write_all(format!("{text}\n").as_bytes())
+            .await
+            .with_context(|| "Failed to write to player")?;
+        self.writer
+            .flush()
+            .await
+            .with_context(|| "Failed to write to player")
+    }
+}
@@ -0,0 +1,262 @@
+//! # Text Engine Module
+//!
+//! This module drives engines speaking a line-based text protocol modelled after UCI.
+//!
+//! The adapter translates between the text protocol and the messages of the raw protocol, and is handed to
+//! the handshake as any other connection: the turns of the server become `go` commands,
+//! and the `bestmove` answers become the choice of a movement.
+//!
+//! ## Text Protocol
+//! Every command and answer is a single line. Lines the adapter does not expect are ignored,
+//! so engines are free to print `info` lines.
+//!
+//! | Server                            | Engine                                      |
+//! | --------------------------------- | ------------------------------------------- |
+//! | `sternhalma`                      | `id name <NAME>` (optional), `sternhalmaok` |
+//! | `isready`                         | `readyok`                                   |
+//! | `position startpos moves <M> ...` |                                             |
+//! | `go movetime <MILLISECONDS>`      | `bestmove <M>`                              |
+//! | `quit`                            |                                             |
+//!
+//! Like every client, engines see the board from the side of `Player1`: their pieces start in the cells of
+//! [`PLAYER1_STARTING_POSITIONS`](crate::sternhalma::board::lut::PLAYER1_STARTING_POSITIONS).
+//! `position` lists every movement played since the start, the last one being the most recent.
+//! The engine is always the one to move after them, so it played every other movement counting back from the last.
+//!
+//! Movements are written as the start and end cells, without separator (e.g. `m5l5`).
+//! A cell is written as a letter for its first coordinate (`a` for 0 to `q` for 16)
+//! followed by its second coordinate plus one (`1` to `17`): `[12, 4]` is `m5`.
+//!
+//! ## Key Components
+//! - [`connection`]: Adapter turning the standard streams of an engine into a client connection.
+//! - [`cell_notation`] and [`parse_movement`]: Notation of cells and movements.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use futures::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::{
+    codec::{FramedRead, LinesCodec},
+    sync::PollSender,
+};
+
+use crate::sternhalma::board::{BOARD_LENGTH, HexIdx, movement::MovementIndices};
+
+use super::{
+    client::{ClientSink, ClientStream},
+    protocol::{PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage},
+};
+
+/// Maximum length of a line written by an engine
+const MAX_LINE_LENGTH: usize = 4096;
+/// Capacity of the channels between the adapter and the client task
+const CHANNEL_CAPACITY: usize = 32;
+/// Time an engine has to answer `sternhalma` and `isready`
+const INIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time an engine has to answer `go`, on top of the requested move time
+const BESTMOVE_GRACE: Duration = Duration::from_secs(5);
+
+/// Writes a cell in the notation of the text protocol
+pub fn cell_notation([q, r]: HexIdx) -> String {
+    format!("{}{}", (b'a' + q as u8) as char, r + 1)
+}
+
+/// Writes a movement in the notation of the text protocol
+pub fn movement_notation([from, to]: MovementIndices) -> String {
+    format!("{}{}", cell_notation(from), cell_notation(to))
+}
+
+/// Parses a cell at the start of a string, returning it along with the rest of the string
+fn parse_cell(notation: &str) -> Option<(HexIdx, &str)> {
+    let mut chars = notation.chars();
+    let q = chars.next().filter(char::is_ascii_lowercase)? as usize - 'a' as usize;
+    let rest = chars.as_str();
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let r = rest[..digits].parse::<usize>().ok()?.checked_sub(1)?;
+    (q < BOARD_LENGTH && r < BOARD_LENGTH).then_some(([q, r], &rest[digits..]))
+}
+
+/// Parses a movement written in the notation of the text protocol
+pub fn parse_movement(notation: &str) -> Option<MovementIndices> {
+    let (from, rest) = parse_cell(notation)?;
+    let (to, rest) = parse_cell(rest)?;
+    rest.is_empty().then_some([from, to])
+}
+
+/// Turns the standard streams of an engine speaking the text protocol into a client connection
+///
+/// `movetime` is the time the engine is asked to think about each movement.
+/// The connection is closed if the engine stops answering or does not follow the protocol.
+pub fn connection<R, W>(reader: R, writer: W, movetime: Duration) -> (ClientStream, ClientSink)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (in_tx, in_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let (out_tx, out_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let adapter = Adapter {
+        lines: FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_LINE_LENGTH)),
+        writer,
+        movetime,
+        moves: Vec::new(),
+    };
+    tokio::spawn(async move {
+        if let Err(e) = adapter.run(in_tx, out_rx).await {
+            log::warn!("Text engine connection closed: {e:?}");
+        }
+    });
+
+    let stream: ClientStream = Box::pin(ReceiverStream::new(in_rx).map(Ok));
+    let sink: ClientSink = Box::pin(PollSender::new(out_tx).sink_map_err(|e| anyhow!(e)));
+    (stream, sink)
+}
+
+/// Adapter between an engine speaking the text protocol and its client task
+struct Adapter<R, W> {
+    /// Lines written by the engine
+    lines: FramedRead<R, LinesCodec>,
+    /// Input of the engine
+    writer: W,
+    /// Time the engine is asked to think about each movement
+    movetime: Duration,
+    /// Movements played since the start of the game
+    moves: Vec<MovementIndices>,
+}
+
+impl<R, W> Adapter<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    async fn run(
+        mut self,
+        in_tx: mpsc::Sender<RemoteInMessage>,
+        mut out_rx: mpsc::Receiver<RemoteOutMessage>,
+    ) -> Result<()> {
+        let name = self.initialize().await?;
+        in_tx
+            .send(RemoteInMessage::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                client_name: name,
+                capabilities: vec![],
+            })
+            .await
+            .with_context(|| "Client closed")?;
+
+        while let Some(message) = out_rx.recv().await {
+            match message {
+                RemoteOutMessage::Reject { reason } => bail!("Rejected by the server: {reason}"),
+                RemoteOutMessage::GameState { history, .. } => {
+                    self.moves = history.into_iter().map(|(_, movement)| movement).collect();
+                }
+                RemoteOutMessage::Movement { movement, .. } => self.moves.push(movement),
+                RemoteOutMessage::TakenBack { movements, .. } => {
+                    let remaining = self.moves.len().saturating_sub(movements.len());
+                    self.moves.truncate(remaining);
+                }
+                RemoteOutMessage::Turn { movements } => {
+                    let choice = self.best_movement(&movements).await?;
+                    in_tx.send(choice).await.with_context(|| "Client closed")?;
+                }
+                RemoteOutMessage::Disconnect { .. } => break,
+                _ => {}
+            }
+        }
+
+        self.send("quit").await
+    }
+
+    /// Introduces the server to the engine and waits for it to be ready
+    ///
+    /// Returns the name announced by the engine.
+    async fn initialize(&mut self) -> Result<Option<String>> {
+        self.send("sternhalma").await?;
+        let mut name = None;
+        loop {
+            let line = self.recv(INIT_TIMEOUT).await?;
+            if line == "sternhalmaok" {
+                break;
+            }
+            if let Some(id) = line.strip_prefix("id name ") {
+                name = Some(id.trim().to_string());
+            }
+        }
+        self.send("isready").await?;
+        while self.recv(INIT_TIMEOUT).await? != "readyok" {}
+        Ok(name)
+    }
+
+    /// Asks the engine for its movement among the available ones
+    ///
+    /// Engines answering with a movement that is not available resign.
+    async fn best_movement(&mut self, movements: &[MovementIndices]) -> Result<RemoteInMessage> {
+        let mut position = "position startpos".to_string();
+        if !self.moves.is_empty() {
+            position.push_str(" moves");
+            for movement in &self.moves {
+                position.push(' ');
+                position.push_str(&movement_notation(*movement));
+            }
+        }
+        self.send(&position).await?;
+        self.send(&format!("go movetime {}", self.movetime.as_millis()))
+            .await?;
+
+        let timeout = self.movetime + BESTMOVE_GRACE;
+        let best = loop {
+            let line = self.recv(timeout).await?;
+            if let Some(best) = line.strip_prefix("bestmove ") {
+                break best
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+            }
+        };
+        let index = parse_movement(&best)
+            .and_then(|best| movements.iter().position(|movement| *movement == best));
+        Ok(match index {
+            Some(movement_index) => RemoteInMessage::Choice { movement_index },
+            None => {
+                log::warn!("Text engine played unavailable movement {best}, resigning");
+                RemoteInMessage::Resign
+            }
+        })
+    }
+
+    /// Writes a line to the engine
+    async fn send(&mut self, line: &str) -> Result<()> {
+        log::trace!("Text engine <- {line}");
+        self.writer
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .with_context(|| "Failed to write to engine")?;
+        self.writer
+            .flush()
+            .await
+            .with_context(|| "Failed to write to engine")
+    }
+
+    /// Reads the next non-empty line written by the engine
+    async fn recv(&mut self, timeout: Duration) -> Result<String> {
+        loop {
+            let line = tokio::time::timeout(timeout, self.lines.next())
+                .await
+                .map_err(|_| anyhow!("Engine did not answer within {timeout:?}"))?
+                .ok_or(anyhow!("Engine closed its output"))?
+                .with_context(|| "Failed to read from engine")?;
+            log::trace!("Text engine -> {line}");
+            let line = line.trim();
+            if !line.is_empty() {
+                return Ok(line.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! # TLS Module
+//!
+//! This module terminates TLS for the TCP and HTTP listeners.
+//!
+//! The certificate chain and private key are read from PEM files, and can be reloaded while the server runs
+//! (e.g. on `SIGHUP` after a renewal). Connections already established keep the certificate they negotiated.
+//!
+//! ## Key Components
+//! - [`Tls`]: Reloadable TLS settings of the server.
+//! - [`TlsListener`]: Listener accepting TLS connections, served by Axum.
+
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_rustls::{TlsAcceptor, server::TlsStream};
+
+/// Maximum time a client has to complete the TLS handshake
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reloadable TLS settings of the server
+#[derive(Debug)]
+pub struct Tls {
+    /// PEM file holding the certificate chain
+    cert_path: PathBuf,
+    /// PEM file holding the private key
+    key_path: PathBuf,
+    /// Settings built from the last loaded certificate
+    config: RwLock<Arc<rustls::ServerConfig>>,
+}
+
+impl Tls {
+    /// Loads the certificate chain and private key from PEM files
+    pub fn load(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Result<Self> {
+        let cert_path = cert_path.into();
+        let key_path = key_path.into();
+        let config = load_config(&cert_path, &key_path)?;
+        Ok(Self {
+            cert_path,
+            key_path,
+            config: RwLock::new(config),
+        })
+    }
+
+    /// Reads the certificate chain and private key again
+    ///
+    /// New connections use the reloaded certificate. On error, the previous certificate is kept.
+    pub fn reload(&self) -> Result<()> {
+        let config = load_config(&self.cert_path, &self.key_path)?;
+        *self.config.write().expect("TLS lock poisoned") = config;
+        Ok(())
+    }
+
+    /// Acceptor for new connections
+    pub fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.config.read().expect("TLS lock poisoned").clone())
+    }
+
+    /// Performs the TLS handshake of an incoming connection
+    pub async fn accept(&self, stream: TcpStream) -> Result<TlsStream<TcpStream>> {
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, self.acceptor().accept(stream))
+            .await
+            .map_err(|_| anyhow!("TLS handshake timed out"))?
+            .with_context(|| "TLS handshake failed")
+    }
+}
+
+/// Builds the TLS settings of the server from PEM files
+fn load_config(cert_path: &Path, key_path: &Path) -> Result<Arc<rustls::ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificates {}", cert_path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificate found in {}", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("Failed to read private key {}", key_path.display()))?;
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .with_context(|| "Failed to select TLS versions")?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .with_context(|| "Invalid certificate or private key")?;
+    Ok(Arc::new(config))
+}
+
+/// Listener accepting TLS connections
+///
+/// Handshakes run concurrently in their own tasks, so that a slow client does not hold back the others.
+/// Connections failing the handshake are dropped.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    /// Accepts TLS connections on a bound TCP listener
+    pub fn new(listener: TcpListener, tls: Arc<Tls>) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    // The listener was dropped
+                    _ = tx.closed() => break,
+                };
+                let (stream, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::error!("Failed to accept connection: {e:?}");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let tls = tls.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tls.accept(stream).await {
+                        Ok(stream) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Err(e) => log::warn!("Rejected connection from {addr}: {e:?}"),
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accepting task only stops once the listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
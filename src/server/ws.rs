@@ -6,63 +6,107 @@
 
 use axum::{
     extract::{
-        State,
+        Path, State,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
-    response::IntoResponse,
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use bytes::Bytes;
 use futures::{SinkExt, StreamExt, future};
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
 
 use super::{
     client::{ClientSink, ClientStream},
     handshake::{AppState, handle_handshake},
-    protocol::{RemoteInMessage, RemoteOutMessage},
+    http::HttpState,
+    metrics::Transport,
+    protocol::{Encoding, RemoteOutMessage},
 };
 
 /// Axum handler for WebSocket upgrades.
-/// Upgrades the HTTP connection to a WebSocket connection.
-pub async fn ws_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_ws(socket, state))
+/// Upgrades the HTTP connection to a WebSocket connection to the default room.
+///
+/// Clients can select the encoding of messages with the `Sec-WebSocket-Protocol` header
+/// (see [`Encoding::subprotocol`]).
+pub async fn ws_handler(State(state): State<HttpState>, ws: WebSocketUpgrade) -> Response {
+    room_ws_handler(State(state.clone()), Path(state.default_room), ws).await
+}
+
+/// Axum handler for WebSocket upgrades to a given room.
+pub async fn room_ws_handler(
+    State(state): State<HttpState>,
+    Path(id): Path<Uuid>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(room) = state.rooms.get(&id) else {
+        return (StatusCode::NOT_FOUND, format!("Game {id} not found")).into_response();
+    };
+    let app_state = room.app_state;
+    ws.protocols(Encoding::variants().map(|encoding| encoding.subprotocol()))
+        .on_upgrade(|socket| handle_ws(socket, app_state))
+        .into_response()
 }
 
 /// Handles the WebSocket connection.
 ///
-/// This function acts as an adapter, converting the WebSocket stream (of `Message::Binary`)
+/// This function acts as an adapter, converting the WebSocket stream (of `Message::Binary` or `Message::Text`)
 /// into the `RemoteInMessage` and `RemoteOutMessage` types used by the core server logic.
 /// It effectively wraps the WebSocket in the same interface as the TCP connection (`ClientSink` / `ClientStream`)
 /// and then delegates to `handle_handshake`.
+///
+/// Binary frames carry CBOR and text frames carry JSON. Unless an encoding was selected
+/// with a subprotocol, the server replies in the encoding of the first frame sent by the client.
 async fn handle_ws(socket: WebSocket, state: AppState) {
+    // Encoding selected by subprotocol, if any
+    let encoding = Arc::new(OnceLock::new());
+    if let Some(selected) = socket
+        .protocol()
+        .and_then(|protocol| protocol.to_str().ok())
+        .and_then(Encoding::from_subprotocol)
+    {
+        log::debug!("WebSocket client selected {selected:?} encoding");
+        let _ = encoding.set(selected);
+    }
+
     let (ws_write, ws_read) = socket.split();
 
     // Adapter for Stream -> RemoteInMessage
-    let stream = Box::pin(ws_read.map(|msg_res| match msg_res {
-        Ok(Message::Binary(bin)) => RemoteInMessage::from_bytes(&bin),
-        Ok(Message::Close(_)) => Err(anyhow::anyhow!("Connection closed")),
-        Ok(_) => Err(anyhow::anyhow!("Unexpected message type")),
-        Err(e) => Err(anyhow::anyhow!("WS Error: {e}")),
+    // Pings are answered by the WebSocket itself, so neither pings nor pongs reach the client
+    let stream_encoding = Arc::clone(&encoding);
+    let stream = Box::pin(ws_read.filter_map(move |msg_res| {
+        future::ready(match msg_res {
+            Ok(Message::Binary(bin)) => {
+                let _ = stream_encoding.set(Encoding::Cbor);
+                Some(Encoding::Cbor.from_bytes(&bin))
+            }
+            Ok(Message::Text(text)) => {
+                let _ = stream_encoding.set(Encoding::Json);
+                Some(Encoding::Json.from_bytes(text.as_bytes()))
+            }
+            Ok(Message::Ping(_) | Message::Pong(_)) => None,
+            Ok(Message::Close(_)) => Some(Err(anyhow::anyhow!("Connection closed"))),
+            Err(e) => Some(Err(anyhow::anyhow!("WS Error: {e}"))),
+        })
     }));
 
     // Adapter for Sink -> RemoteOutMessage
-    let sink = Box::pin(SinkExt::with(ws_write, |msg: RemoteOutMessage| {
-        let mut buf = Vec::new();
-        // Ciborium to vec
-        let res = ciborium::into_writer(&msg, &mut buf)
-            .map_err(|e| anyhow::anyhow!("Serialization error: {e}"))
-            .map(|_| Message::Binary(Bytes::from(buf)));
+    let sink = Box::pin(SinkExt::with(ws_write, move |msg: RemoteOutMessage| {
+        let res = match encoding.get().copied().unwrap_or_default() {
+            Encoding::Cbor => Encoding::Cbor
+                .to_bytes(&msg)
+                .map(|buf| Message::Binary(Bytes::from(buf))),
+            Encoding::Json => serde_json::to_string(&msg)
+                .map(|text| Message::Text(text.into()))
+                .map_err(|e| anyhow::anyhow!("Serialization error: {e}")),
+        };
         future::ready(res)
     }));
 
-    // Reuse handle_handshake logic
-    // We need to pass ClientSink/Stream. The adapters should match.
-    // Wait, SinkExt::with returns a struct, I need to wrap in Box/Pin.
-    // And Sink must satisfy the trait.
-    // ws_write is SplitSink<WebSocket>.
-    // SinkExt::with takes `self`.
-
     // Boxing helper
     let sink: ClientSink = Box::pin(sink);
     let stream: ClientStream = Box::pin(stream);
 
-    handle_handshake(stream, sink, state).await;
+    handle_handshake(stream, sink, state, Transport::WebSocket).await;
 }
@@ -112,13 +112,25 @@ impl<T> Default for Board<T> {
 #[derive(Debug, Clone, Copy)]
 pub struct InvalidBoardIndex(pub HexIdx);
 
+/// Check if an index lies within the board's bounding square
+#[inline(always)]
+fn in_bounds([i, j]: HexIdx) -> bool {
+    i < BOARD_LENGTH && j < BOARD_LENGTH
+}
+
 impl<T> Board<T> {
     /// Returns a reference to the piece at the specified index on the board
     pub fn get(&self, idx: &HexIdx) -> Result<&Option<T>, InvalidBoardIndex> {
+        if !in_bounds(*idx) {
+            return Err(InvalidBoardIndex(*idx));
+        }
         self[*idx].as_ref().ok_or(InvalidBoardIndex(*idx))
     }
 
     pub fn get_mut(&mut self, idx: &HexIdx) -> Result<&mut Option<T>, InvalidBoardIndex> {
+        if !in_bounds(*idx) {
+            return Err(InvalidBoardIndex(*idx));
+        }
         self[*idx].as_mut().ok_or(InvalidBoardIndex(*idx))
     }
 }
@@ -11,6 +11,8 @@
 use std::fmt::Debug;
 
 use anyhow::Result;
+use itertools::Itertools;
+use thiserror::Error;
 
 use crate::sternhalma::board::{
     BOARD_LENGTH, Board, HexDirection, HexIdx, InvalidBoardIndex, player::Player,
@@ -145,16 +147,33 @@ impl<T: PartialEq> Board<T> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Error when validating a movement
+///
+/// The messages do not mention board indices because they may be shown
+/// to clients that use a different coordinate perspective.
+#[derive(Debug, Clone, Copy, Error)]
 pub enum MovementError {
     /// Initial position is empty
+    #[error("starting position is empty")]
     EmptyInit,
     /// One of the indices is outside the board
+    #[error("position is outside of the board")]
     InvalidIndex(HexIdx),
     /// One of the indices is occupied
+    #[error("position is occupied")]
     Occupied(HexIdx),
     /// The hopping sequence is too short
+    #[error("hopping path is too short")]
     ShortHopping(usize),
+    /// Single move to a cell that is not adjacent
+    #[error("destination is not adjacent to the starting position")]
+    NotAdjacent(HexIdx),
+    /// Hop that does not jump over a single piece
+    #[error("hop does not jump over a single piece")]
+    InvalidHop(HexIdx),
+    /// Hopping path visits the same cell twice
+    #[error("hopping path visits the same position twice")]
+    RepeatedIndex(HexIdx),
 }
 
 impl<T> Board<T> {
@@ -221,6 +240,47 @@ impl<T> Board<T> {
     }
 }
 
+impl<T> Board<T> {
+    /// Check that the movement follows the rules of the game
+    ///
+    /// A single move must go to an adjacent cell, and every hop of a hopping path
+    /// must jump over a single piece without visiting a cell twice.
+    /// If legal, returns the movement and the player that would perform it.
+    pub fn validate_movement_rules<'a, 'b>(
+        &'a self,
+        movement: &'b Movement,
+    ) -> Result<(&'b Movement, &'a T), MovementError> {
+        // Check positions first
+        let validated = self.validate_movement(movement)?;
+
+        match movement {
+            Movement::Move { from, to } => {
+                let adjacent = HexDirection::variants().into_iter().any(|direction| {
+                    self.nearest_neighbor(*from, direction)
+                        .is_some_and(|(idx, _)| idx == *to)
+                });
+                if !adjacent {
+                    return Err(MovementError::NotAdjacent(*to));
+                }
+            }
+            Movement::Hops { path } => {
+                if let Some(idx) = path.iter().duplicates().next() {
+                    return Err(MovementError::RepeatedIndex(*idx));
+                }
+                if let Some((_, to)) = path
+                    .iter()
+                    .tuple_windows()
+                    .find(|(from, to)| !self.available_hops_from(**from).contains(*to))
+                {
+                    return Err(MovementError::InvalidHop(*to));
+                }
+            }
+        }
+
+        Ok(validated)
+    }
+}
+
 /// Compact movement representation
 /// Pair composed of `from` and `to` indices
 pub type MovementIndices = [HexIdx; 2];
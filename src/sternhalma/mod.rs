@@ -13,6 +13,7 @@
 use std::fmt::{Debug, Display};
 
 use anyhow::Result;
+use thiserror::Error;
 
 use crate::sternhalma::board::{
     Board, HexIdx, goal_indices,
@@ -30,6 +31,15 @@ use serde::{Deserialize, Serialize};
 
 pub type Scores = [usize; PLAYER_COUNT];
 
+/// Game variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// Two players starting on opposite points of the star with 15 pieces each
+    #[default]
+    Classic,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum GameResult {
@@ -42,6 +52,78 @@ pub enum GameResult {
         total_turns: usize,
         scores: Scores,
     },
+    /// A player abandoned the game and forfeited
+    Forfeit {
+        winner: Player,
+        total_turns: usize,
+        scores: Scores,
+    },
+    /// A player resigned
+    Resigned {
+        winner: Player,
+        total_turns: usize,
+        scores: Scores,
+    },
+    /// Players agreed to a draw
+    Draw {
+        total_turns: usize,
+        scores: Scores,
+    },
+    /// The server shut down before the game finished
+    Aborted {
+        total_turns: usize,
+        scores: Scores,
+    },
+}
+
+impl GameResult {
+    /// Name of the type of result, as serialized in its `type` field
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            GameResult::Finished { .. } => "finished",
+            GameResult::MaxTurns { .. } => "max_turns",
+            GameResult::Forfeit { .. } => "forfeit",
+            GameResult::Resigned { .. } => "resigned",
+            GameResult::Draw { .. } => "draw",
+            GameResult::Aborted { .. } => "aborted",
+        }
+    }
+
+    /// Winner of the game, if any
+    pub const fn winner(&self) -> Option<Player> {
+        match self {
+            GameResult::Finished { winner, .. }
+            | GameResult::Forfeit { winner, .. }
+            | GameResult::Resigned { winner, .. } => Some(*winner),
+            GameResult::MaxTurns { .. } | GameResult::Draw { .. } | GameResult::Aborted { .. } => {
+                None
+            }
+        }
+    }
+
+    /// Number of turns played
+    pub const fn total_turns(&self) -> usize {
+        match self {
+            GameResult::Finished { total_turns, .. }
+            | GameResult::MaxTurns { total_turns, .. }
+            | GameResult::Forfeit { total_turns, .. }
+            | GameResult::Resigned { total_turns, .. }
+            | GameResult::Draw { total_turns, .. }
+            | GameResult::Aborted { total_turns, .. } => *total_turns,
+        }
+    }
+
+    /// Final scores
+    pub const fn scores(&self) -> Scores {
+        match self {
+            GameResult::Finished { scores, .. }
+            | GameResult::MaxTurns { scores, .. }
+            | GameResult::Forfeit { scores, .. }
+            | GameResult::Resigned { scores, .. }
+            | GameResult::Draw { scores, .. }
+            | GameResult::Aborted { scores, .. } => *scores,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -148,6 +230,16 @@ impl Game {
         &self.history
     }
 
+    /// Iterate over the movements in the game history along with the player who made them
+    ///
+    /// Players alternate turns, starting with `Player1`.
+    pub fn iter_history(&self) -> impl Iterator<Item = (Player, MovementIndices)> {
+        Player::variants()
+            .into_iter()
+            .cycle()
+            .zip(self.history.iter().copied())
+    }
+
     pub fn history_bytes(&self) -> usize {
         self.history.capacity() * std::mem::size_of::<[HexIdx; 2]>()
     }
@@ -160,13 +252,16 @@ impl Default for Game {
 }
 
 /// Error that can occur during game operations
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Error)]
 pub enum GameError {
     /// Movement error
+    #[error("invalid movement: {0}")]
     Movement(MovementError),
     /// Movement made out of turn
+    #[error("piece does not belong to the player in turn")]
     OutOfTurn,
     /// Movement made after the game is finished
+    #[error("game has already finished")]
     GameFinished,
 }
 
@@ -221,8 +316,10 @@ impl Game {
         }
     }
 
-    /// Apply movement to the current game
-    pub fn apply_movement(&mut self, movement: &Movement) -> Result<GameStatus, GameError> {
+    /// Check that a movement is legal for the current turn's player
+    ///
+    /// Returns the compact representation of the movement
+    pub fn validate_movement(&self, movement: &Movement) -> Result<MovementIndices, GameError> {
         match self.status {
             GameStatus::Finished { .. } => Err(GameError::GameFinished),
             GameStatus::Playing {
@@ -232,7 +329,7 @@ impl Game {
                 // Validate movement
                 let (movement, player) = self
                     .board
-                    .validate_movement(movement)
+                    .validate_movement_rules(movement)
                     .map_err(GameError::Movement)?;
 
                 // Check if the movement is made by the current player
@@ -240,16 +337,19 @@ impl Game {
                     return Err(GameError::OutOfTurn);
                 }
 
-                // Apply the movement to the board
-                unsafe {
-                    self.apply_movement_unchecked(&movement.into());
-                }
-
-                Ok(self.status)
+                Ok(movement.into())
             }
         }
     }
 
+    /// Apply movement to the current game
+    pub fn apply_movement(&mut self, movement: &Movement) -> Result<GameStatus, GameError> {
+        let movement = self.validate_movement(movement)?;
+
+        // Apply the movement to the board
+        unsafe { Ok(self.apply_movement_unchecked(&movement)) }
+    }
+
     /// Apply movement in the game without validating it or the player
     ///
     /// # Safety
@@ -269,4 +369,38 @@ impl Game {
 
         self.status
     }
+
+    /// Undo the last movement of the game
+    ///
+    /// The game goes back to the turn of the player who made the movement,
+    /// even if the movement had finished the game.
+    /// Returns the undone movement along with the player who made it.
+    pub fn undo_movement(&mut self) -> Option<(Player, MovementIndices)> {
+        let movement = self.history.pop()?;
+        let player = Player::variants()[self.history.len() % PLAYER_COUNT];
+
+        // Move the piece back to its starting position
+        let [from, to] = movement;
+        unsafe {
+            self.board.apply_movement_unchecked(&[to, from]);
+        }
+
+        // Revert game scores
+        let mut scores = self.status.scores();
+        let goal = goal_indices(&player);
+        if goal.contains(&to) {
+            scores[player as usize] -= 1;
+        }
+        if goal.contains(&from) {
+            scores[player as usize] += 1;
+        }
+
+        self.status = GameStatus::Playing {
+            player,
+            turns: self.status.turns() - 1,
+            scores,
+        };
+
+        Some((player, movement))
+    }
 }
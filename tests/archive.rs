@@ -0,0 +1,121 @@
+use std::{path::Path, process::Command, time::Duration};
+
+use assert_matches::assert_matches;
+use common::{TestServer, hello};
+use serde_json::Value;
+use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use uuid::Uuid;
+
+mod common;
+
+/// Runs the archive subcommand of the server binary and returns its output
+fn archive_command(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_sternhalma-server"))
+        .arg("archive")
+        .args(args)
+        .output()
+        .expect("Failed to run archive subcommand");
+    assert!(
+        output.status.success(),
+        "Archive subcommand failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Waits for the index of the archive to be written
+async fn wait_index(dir: &Path) {
+    for _ in 0..50 {
+        if dir.join("index.jsonl").exists() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Archive index was not written");
+}
+
+#[tokio::test]
+async fn test_archive_finished_game() {
+    let archive_dir = std::env::temp_dir().join(format!("sternhalma-archive-{}", Uuid::new_v4()));
+    let archive_arg = archive_dir.to_str().unwrap().to_string();
+    let server =
+        TestServer::with_args(&["--archive-dir", &archive_arg]).expect("Failed to start server");
+
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Player 1 plays a movement, then Player 2 resigns
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+    client2.send(RemoteInMessage::Resign).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished { .. }
+    );
+    drop(client1);
+    drop(client2);
+    wait_index(&archive_dir).await;
+
+    // The game is listed in the index
+    let listed = archive_command(&["list", "--archive-dir", &archive_arg, "--json"]);
+    let entries: Vec<Value> = listed
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["result"], "resigned");
+    assert_eq!(entries[0]["winner"], "player1");
+    assert_eq!(entries[0]["total_turns"], 1);
+    let id = entries[0]["id"].as_str().unwrap().to_string();
+
+    // Filters select the matching games
+    let listed = archive_command(&[
+        "list",
+        "--archive-dir",
+        &archive_arg,
+        "--winner",
+        "player1",
+        "--player",
+        "test-client",
+    ]);
+    assert!(listed.contains(&id));
+    let listed = archive_command(&["list", "--archive-dir", &archive_arg, "--result", "draw"]);
+    assert!(listed.is_empty());
+
+    // The full record holds the seats, history and timing of the game
+    let record: Value = serde_json::from_str(&archive_command(&[
+        "show",
+        "--archive-dir",
+        &archive_arg,
+        &id,
+    ]))
+    .unwrap();
+    assert_eq!(record["variant"], "classic");
+    assert_eq!(record["seats"].as_array().unwrap().len(), 2);
+    assert_eq!(record["seats"][0]["name"], "test-client");
+    assert_eq!(record["history"].as_array().unwrap().len(), 1);
+    assert!(record["started_at"].as_u64().unwrap() <= record["finished_at"].as_u64().unwrap());
+    assert_eq!(record["result"]["type"], "resigned");
+
+    drop(server);
+    let _ = std::fs::remove_dir_all(archive_dir);
+}
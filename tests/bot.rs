@@ -0,0 +1,127 @@
+use assert_matches::assert_matches;
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::{PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::sternhalma::board::player::Player;
+
+mod common;
+
+#[tokio::test]
+async fn test_play_against_bot() {
+    // The bot takes the first seat, leaving the second one to the client
+    let server = TestServer::with_args(&["--bot", "greedy:1"]).expect("Failed to start server");
+
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    assert_matches!(
+        client.recv().await.unwrap(),
+        RemoteOutMessage::Welcome { .. }
+    );
+    client.recv_game_state().await.unwrap();
+    match client.recv_game_started().await.unwrap() {
+        RemoteOutMessage::GameStarted {
+            you_move_first,
+            opponents,
+            ..
+        } => {
+            assert!(!you_move_first);
+            assert_eq!(opponents[0].name.as_deref(), Some("greedy bot"));
+        }
+        _ => unreachable!(),
+    }
+
+    // The bot answers every movement of the client
+    for _ in 0..3 {
+        assert_matches!(
+            client.recv().await.unwrap(),
+            RemoteOutMessage::Movement {
+                player: Player::Player2,
+                ..
+            }
+        );
+        assert_matches!(client.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+        client
+            .send(RemoteInMessage::Choice { movement_index: 0 })
+            .await
+            .unwrap();
+        assert_matches!(
+            client.recv().await.unwrap(),
+            RemoteOutMessage::Movement {
+                player: Player::Player1,
+                ..
+            }
+        );
+    }
+
+    // Draw offers are declined
+    client.send(RemoteInMessage::OfferDraw).await.unwrap();
+    loop {
+        match client.recv().await.unwrap() {
+            RemoteOutMessage::DrawDeclined { player } => {
+                assert_eq!(player, Player::Player2);
+                break;
+            }
+            RemoteOutMessage::DrawOffered { .. }
+            | RemoteOutMessage::Movement { .. }
+            | RemoteOutMessage::Turn { .. } => {}
+            other => panic!("Expected DrawDeclined, got {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_absent_player_replaced_by_bot() {
+    let server =
+        TestServer::with_args(&["--disconnect-timeout", "1", "--replace-absent", "search"])
+            .expect("Failed to start server");
+
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    let session_id = match client1.recv().await.unwrap() {
+        RemoteOutMessage::Welcome { session_id, .. } => session_id,
+        other => panic!("Expected Welcome, got {other:?}"),
+    };
+    client1.recv_game_state().await.unwrap();
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Player 1 leaves on their turn and does not come back
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+    drop(client1);
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::OpponentDisconnected { .. }
+    );
+
+    // A bot takes the seat and plays the turn instead of Player 1 forfeiting
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::OpponentReconnected { .. }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement {
+            player: Player::Player2,
+            ..
+        }
+    );
+    assert_matches!(client2.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // The seat now belongs to the bot
+    let mut client1 = server.client().await.expect("Failed to reconnect client 1");
+    client1
+        .send(RemoteInMessage::Reconnect {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        })
+        .await
+        .unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Reject { .. }
+    );
+}
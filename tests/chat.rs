@@ -0,0 +1,95 @@
+use assert_matches::assert_matches;
+use common::{TestServer, start_game};
+use sternhalma_server::server::protocol::{
+    CHAT_MESSAGE_LENGTH, ErrorCode, RemoteInMessage, RemoteOutMessage,
+};
+use sternhalma_server::sternhalma::board::player::Player;
+
+mod common;
+
+fn chat(text: &str) -> RemoteInMessage {
+    RemoteInMessage::Chat {
+        text: text.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_chat_between_players() {
+    let server = TestServer::new().expect("Failed to start server");
+    let (mut client1, mut client2) = start_game(&server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    client2.send(chat("good luck")).await.unwrap();
+
+    // Both players receive the message, with the sender in their own perspective
+    match client1.recv().await.unwrap() {
+        RemoteOutMessage::Chat {
+            player,
+            text,
+            timestamp,
+        } => {
+            assert_eq!(player, Player::Player2);
+            assert_eq!(text, "good luck");
+            assert!(timestamp > 0);
+        }
+        other => panic!("Expected Chat, got {:?}", other),
+    }
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Chat {
+            player: Player::Player1,
+            ..
+        }
+    );
+
+    // Oversized messages are rejected
+    client1
+        .send(chat(&"a".repeat(CHAT_MESSAGE_LENGTH + 1)))
+        .await
+        .unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::InvalidChatMessage,
+            ..
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_chat_rate_limit() {
+    let server = TestServer::new().expect("Failed to start server");
+    let (mut client1, _client2) = start_game(&server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // The first messages go through until the limit is reached
+    let mut rate_limited = false;
+    for i in 0..10 {
+        client1.send(chat(&format!("spam {i}"))).await.unwrap();
+        match client1.recv().await.unwrap() {
+            RemoteOutMessage::Chat { .. } => assert!(!rate_limited),
+            RemoteOutMessage::Error {
+                code: ErrorCode::ChatRateLimited,
+                ..
+            } => rate_limited = true,
+            other => panic!("Expected Chat or Error, got {:?}", other),
+        }
+    }
+    assert!(rate_limited, "Player should have been rate limited");
+}
+
+#[tokio::test]
+async fn test_muted_chat() {
+    let server = TestServer::with_args(&["--mute-chat"]).expect("Failed to start server");
+    let (mut client1, _client2) = start_game(&server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    client1.send(chat("hello?")).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::ChatMuted,
+            ..
+        }
+    );
+}
@@ -1,3 +1,6 @@
+// Not every test binary uses every helper
+#![allow(dead_code)]
+
 use anyhow::{Context, Result, anyhow};
 use std::process::{Child, Command, Stdio};
 use std::sync::Once;
@@ -5,28 +8,91 @@ use std::thread;
 use std::time::Duration;
 
 use futures::{SinkExt, StreamExt};
-use sternhalma_server::server::protocol::{ClientCodec, RemoteInMessage, RemoteOutMessage};
-use tokio::net::TcpStream;
+use sternhalma_server::server::protocol::{
+    ClientCodec, PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, UnixStream};
 use tokio_util::codec::Framed;
+use uuid::Uuid;
 
 static BUILD_SERVER: Once = Once::new();
 
+/// Hello message for the latest protocol version
+pub fn hello() -> RemoteInMessage {
+    RemoteInMessage::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        client_name: Some("test-client".to_string()),
+        capabilities: vec![],
+    }
+}
+
+/// Connects a player and returns its session id
+pub async fn join(server: &TestServer) -> (TestClient, Uuid) {
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.expect("Failed to send Hello");
+    let session_id = match client.recv().await.expect("Failed to receive Welcome") {
+        RemoteOutMessage::Welcome { session_id, .. } => session_id,
+        other => panic!("Expected Welcome, got {other:?}"),
+    };
+    client
+        .recv_game_state()
+        .await
+        .expect("Failed to receive GameState");
+    (client, session_id)
+}
+
+/// Reconnects a player to its session
+pub async fn reconnect(server: &TestServer, session_id: Uuid) -> TestClient {
+    let mut client = server.client().await.expect("Failed to connect client");
+    client
+        .send(RemoteInMessage::Reconnect {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        })
+        .await
+        .expect("Failed to send Reconnect");
+    match client.recv().await.expect("Failed to receive Welcome") {
+        RemoteOutMessage::Welcome {
+            session_id: resumed,
+            ..
+        } => assert_eq!(resumed, session_id),
+        other => panic!("Expected Welcome, got {other:?}"),
+    }
+    client
+}
+
+/// Connects both players and waits for the game to start
+pub async fn start_game(server: &TestServer) -> (TestClient, TestClient) {
+    let (mut client1, _) = join(server).await;
+    let (mut client2, _) = join(server).await;
+    client1
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted");
+    client2
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted");
+    (client1, client2)
+}
+
 pub struct TestServer {
     process: Child,
+    /// Address of the TCP listener, or path of the Unix socket
     pub address: String,
+    /// Whether clients connect to the Unix socket
+    unix: bool,
 }
 
 impl TestServer {
     pub fn new() -> Result<Self> {
-        // Build the server binary once ensuring it's up to date
-        BUILD_SERVER.call_once(|| {
-            let status = Command::new("cargo")
-                .args(["build", "--bin", "sternhalma-server"])
-                .status()
-                .expect("Failed to build server");
-            assert!(status.success(), "Server build failed");
-        });
+        Self::with_args(&[])
+    }
 
+    /// Starts a server with additional command line arguments
+    pub fn with_args(args: &[&str]) -> Result<Self> {
         // Use a random port by letting the OS verify availability, or just use port 0 logic if supported by server?
         // Server takes "IP:PORT" or socket path.
         // We can pick a random port. To check availability, we can bind to 0 and get the port, then close.
@@ -35,34 +101,60 @@ impl TestServer {
             listener.local_addr()?.port()
         };
         let address = format!("127.0.0.1:{}", port);
+        Self::spawn(&["--tcp", &address], args, address.clone(), false)
+    }
+
+    /// Starts a server listening on a Unix socket only, with additional command line arguments
+    ///
+    /// No port has to be allocated, and the socket is removed by the server when it exits.
+    pub fn with_unix_socket(args: &[&str]) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("sternhalma-{}.sock", uuid::Uuid::new_v4()));
+        let address = path.to_str().context("Invalid socket path")?.to_string();
+        Self::spawn(&["--unix", &address], args, address.clone(), true)
+    }
+
+    fn spawn(listener: &[&str], args: &[&str], address: String, unix: bool) -> Result<Self> {
+        // Build the server binary once ensuring it's up to date
+        BUILD_SERVER.call_once(|| {
+            // The package variables set for the test would invalidate build scripts tracking them (e.g. ring)
+            let mut command = Command::new("cargo");
+            for (key, _) in std::env::vars() {
+                if key.starts_with("CARGO_PKG_") || key.starts_with("CARGO_MANIFEST_") {
+                    command.env_remove(key);
+                }
+            }
+            let status = command
+                .args(["build", "--bin", "sternhalma-server"])
+                .status()
+                .expect("Failed to build server");
+            assert!(status.success(), "Server build failed");
+        });
 
         // Spawn server
         let path = env!("CARGO_BIN_EXE_sternhalma-server");
         let mut process = Command::new(path)
-            .arg("--tcp")
-            .arg(&address)
+            .args(listener)
             .arg("--max-turns")
             .arg("100")
+            .args(args)
             .env("RUST_LOG", "debug")
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .spawn()
             .context("Failed to spawn server")?;
 
-        // Wait for "Listening" message on stdout or stderr (env_logger usually prints to stderr)
-        // Sternhalma server header logs might be on stderr?
-        // src/bin/sternhalma-server.rs: env_logger::init(); logic.
-        // logs are typically stderr.
-        // The server prints "Listening at ..." using log::info!.
-        // Default env_logger writes to stderr.
-
         // Wait for server to be ready by polling the connection
         let mut attempts = 0;
         let max_attempts = 50;
         let mut started = false;
 
         while attempts < max_attempts {
-            if std::net::TcpStream::connect(&address).is_ok() {
+            let connected = if unix {
+                std::os::unix::net::UnixStream::connect(&address).is_ok()
+            } else {
+                std::net::TcpStream::connect(&address).is_ok()
+            };
+            if connected {
                 started = true;
                 break;
             }
@@ -93,19 +185,60 @@ impl TestServer {
         // Give it a tiny bit more time to be fully ready accepting connections
         thread::sleep(Duration::from_millis(100));
 
-        Ok(Self { process, address })
+        Ok(Self {
+            process,
+            address,
+            unix,
+        })
     }
 
     pub async fn client(&self) -> Result<TestClient> {
-        let stream = TcpStream::connect(&self.address)
-            .await
-            .context("Failed to connect to server")?;
+        let stream: Box<dyn Connection> = if self.unix {
+            Box::new(
+                UnixStream::connect(&self.address)
+                    .await
+                    .context("Failed to connect to server")?,
+            )
+        } else {
+            Box::new(
+                TcpStream::connect(&self.address)
+                    .await
+                    .context("Failed to connect to server")?,
+            )
+        };
         Ok(TestClient {
             framed: Framed::new(stream, ClientCodec::new()),
         })
     }
 }
 
+impl TestServer {
+    /// Sends a signal to the server process
+    pub fn signal(&self, signal: &str) -> Result<()> {
+        let status = Command::new("kill")
+            .arg(format!("-{signal}"))
+            .arg(self.process.id().to_string())
+            .status()
+            .context("Failed to run kill")?;
+        if !status.success() {
+            return Err(anyhow!("Failed to send {signal} to server"));
+        }
+        Ok(())
+    }
+
+    /// Waits for the server process to exit and returns whether it exited successfully
+    pub fn wait_exit(&mut self, timeout: Duration) -> Result<bool> {
+        let deadline = std::time::Instant::now() + timeout;
+        while std::time::Instant::now() < deadline {
+            if let Some(status) = self.process.try_wait()? {
+                return Ok(status.success());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        Err(anyhow!("Server did not exit within {timeout:?}"))
+    }
+}
+
 impl Drop for TestServer {
     fn drop(&mut self) {
         let _ = self.process.kill();
@@ -113,8 +246,13 @@ impl Drop for TestServer {
     }
 }
 
+/// Stream of a client connection
+pub trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
 pub struct TestClient {
-    framed: Framed<TcpStream, ClientCodec>,
+    framed: Framed<Box<dyn Connection>, ClientCodec>,
 }
 
 impl TestClient {
@@ -132,4 +270,20 @@ impl TestClient {
             None => Err(anyhow!("Connection closed")),
         }
     }
+
+    /// Receives the announcement sent once all players are connected
+    pub async fn recv_game_started(&mut self) -> Result<RemoteOutMessage> {
+        match self.recv().await? {
+            msg @ RemoteOutMessage::GameStarted { .. } => Ok(msg),
+            other => Err(anyhow!("Expected GameStarted, got {other:?}")),
+        }
+    }
+
+    /// Receives the game state sent after every Welcome
+    pub async fn recv_game_state(&mut self) -> Result<RemoteOutMessage> {
+        match self.recv().await? {
+            msg @ RemoteOutMessage::GameState { .. } => Ok(msg),
+            other => Err(anyhow!("Expected GameState, got {other:?}")),
+        }
+    }
 }
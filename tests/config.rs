@@ -0,0 +1,134 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use assert_matches::assert_matches;
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::{ErrorCode, RemoteInMessage, RemoteOutMessage};
+
+mod common;
+
+/// Writes a configuration file in the temporary directory
+fn write_config(contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sternhalma-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, contents).expect("Failed to write configuration file");
+    path
+}
+
+/// Runs the server to check a configuration file
+fn check_config(contents: &str) -> Output {
+    let path = write_config(contents);
+    let output = Command::new(env!("CARGO_BIN_EXE_sternhalma-server"))
+        .arg("--config")
+        .arg(&path)
+        .arg("--check-config")
+        .output()
+        .expect("Failed to run server");
+    std::fs::remove_file(path).unwrap();
+    output
+}
+
+#[test]
+fn test_check_config() {
+    let output = check_config(
+        r#"
+        [listeners]
+        tcp = "127.0.0.1:0"
+        unix = "/tmp/sternhalma-check.sock"
+        unix_mode = 0o660
+
+        [room]
+        max_turns = 200
+
+        [limits]
+        disconnect_timeout = 30
+
+        [engines]
+        raw = ["./my-engine --depth 3"]
+
+        [logging]
+        level = "info"
+        "#,
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("valid"));
+
+    // Misspelled settings are reported rather than ignored
+    let output = check_config("[room]\nmax_turn = 200\n");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("max_turn"));
+
+    // The merged settings are checked as well
+    let output = check_config("[room]\nmax_turns = 200\n");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must be provided"));
+    let output = check_config("[listeners]\ntcp = \"127.0.0.1:0\"\ntls_cert = \"cert.pem\"\n");
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+async fn test_config_file_overridden_by_command_line() {
+    let path = write_config(
+        r#"
+        [room]
+        max_turns = 1
+        mute_chat = true
+        "#,
+    );
+    // The test server gives `--max-turns 100` on the command line
+    let server = TestServer::with_args(&["--config", path.to_str().unwrap()])
+        .expect("Failed to start server");
+
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    assert_matches!(
+        client1.recv_game_started().await.unwrap(),
+        RemoteOutMessage::GameStarted {
+            max_turns: Some(100),
+            ..
+        }
+    );
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // Settings left out of the command line are read from the file
+    client1
+        .send(RemoteInMessage::Chat {
+            text: "hello?".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::ChatMuted,
+            ..
+        }
+    );
+    drop(server);
+
+    // Settings turned on by the file can be turned off on the command line
+    let server = TestServer::with_args(&["--config", path.to_str().unwrap(), "--no-mute-chat"])
+        .expect("Failed to start server");
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    client.recv().await.unwrap();
+    client.recv_game_state().await.unwrap();
+    client
+        .send(RemoteInMessage::Chat {
+            text: "hello?".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_matches!(
+        client.recv().await.unwrap(),
+        RemoteOutMessage::Chat { text, .. } if text == "hello?"
+    );
+    std::fs::remove_file(path).unwrap();
+}
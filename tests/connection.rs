@@ -1,6 +1,11 @@
 use assert_matches::assert_matches;
-use common::TestServer;
-use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::{
+    Capability, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage,
+};
+use sternhalma_server::sternhalma::{GameResult, board::player::Player};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
 
 mod common;
 
@@ -13,17 +18,35 @@ async fn test_successful_connection_and_handshake() {
     let mut client = server.client().await.expect("Failed to connect client");
 
     // Send Hello
-    client
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello");
+    client.send(hello()).await.expect("Failed to send Hello");
 
     // Expect Welcome
     let msg = client.recv().await.expect("Failed to receive response");
     match msg {
-        RemoteOutMessage::Welcome { session_id: _ } => {}
+        RemoteOutMessage::Welcome { .. } => {}
         _ => panic!("Expected Welcome message"),
     };
+
+    // Expect the initial game state
+    let msg = client
+        .recv_game_state()
+        .await
+        .expect("Failed to receive game state");
+    match msg {
+        RemoteOutMessage::GameState {
+            board,
+            to_move,
+            turn,
+            history,
+            ..
+        } => {
+            assert_eq!(board.len(), 30);
+            assert_eq!(to_move, Some(Player::Player1));
+            assert_eq!(turn, 0);
+            assert!(history.is_empty());
+        }
+        other => panic!("Expected GameState message, got {:?}", other),
+    }
 }
 
 #[tokio::test]
@@ -32,27 +55,38 @@ async fn test_multiple_players_connection() {
 
     // Player 1
     let mut client1 = server.client().await.expect("Failed to connect client 1");
-    client1
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello 1");
+    client1.send(hello()).await.expect("Failed to send Hello 1");
     let msg1 = client1.recv().await.expect("Failed to receive response 1");
     match msg1 {
-        RemoteOutMessage::Welcome { session_id: _ } => {}
+        RemoteOutMessage::Welcome { .. } => {}
         other => panic!("Unexpected message for client 1: {:?}", other),
     }
 
     // Player 2
     let mut client2 = server.client().await.expect("Failed to connect client 2");
-    client2
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello 2");
+    client2.send(hello()).await.expect("Failed to send Hello 2");
     let msg2 = client2.recv().await.expect("Failed to receive response 2");
     match msg2 {
-        RemoteOutMessage::Welcome { session_id: _ } => {}
+        RemoteOutMessage::Welcome { .. } => {}
         other => panic!("Unexpected message for client 2: {:?}", other),
     }
+
+    // Both players are told the game has started, from their own perspective
+    client1.recv_game_state().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    for (client, first) in [(&mut client1, true), (&mut client2, false)] {
+        match client.recv_game_started().await.unwrap() {
+            RemoteOutMessage::GameStarted {
+                you_move_first,
+                opponents,
+                ..
+            } => {
+                assert_eq!(you_move_first, first);
+                assert_eq!(opponents.len(), 1);
+            }
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[tokio::test]
@@ -61,19 +95,191 @@ async fn test_reject_excess_players() {
 
     // Player 1
     let mut client1 = server.client().await.expect("Failed to connect client 1");
-    client1.send(RemoteInMessage::Hello).await.unwrap();
+    client1.send(hello()).await.unwrap();
     client1.recv().await.unwrap();
 
     // Player 2
     let mut client2 = server.client().await.expect("Failed to connect client 2");
-    client2.send(RemoteInMessage::Hello).await.unwrap();
+    client2.send(hello()).await.unwrap();
     client2.recv().await.unwrap();
 
     // Player 3 (Excess)
     let mut client3 = server.client().await.expect("Failed to connect client 3");
-    client3.send(RemoteInMessage::Hello).await.unwrap();
+    client3.send(hello()).await.unwrap();
 
     // Should receive Reject
     let msg3 = client3.recv().await.expect("Failed to receive response 3");
     assert_matches!(msg3, RemoteOutMessage::Reject { .. });
 }
+
+#[tokio::test]
+async fn test_concurrent_hellos_get_distinct_seats() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Every client says hello before any of them is answered
+    let mut clients = Vec::new();
+    for _ in 0..4 {
+        clients.push(server.client().await.expect("Failed to connect client"));
+    }
+    futures::future::join_all(clients.iter_mut().map(|client| client.send(hello()))).await;
+
+    // Only the clients that got a seat are welcomed, the others are rejected without a Welcome
+    let mut seated = Vec::new();
+    for mut client in clients {
+        match client.recv().await.unwrap() {
+            RemoteOutMessage::Welcome { .. } => seated.push(client),
+            RemoteOutMessage::Reject { .. } => {}
+            other => panic!("Expected Welcome or Reject, got {other:?}"),
+        }
+    }
+    assert_eq!(seated.len(), 2);
+
+    // Both seated clients play, one of them first
+    let mut first = Vec::new();
+    for client in &mut seated {
+        client.recv_game_state().await.unwrap();
+        match client.recv_game_started().await.unwrap() {
+            RemoteOutMessage::GameStarted { you_move_first, .. } => first.push(you_move_first),
+            _ => unreachable!(),
+        }
+    }
+    first.sort();
+    assert_eq!(first, [false, true]);
+}
+
+#[tokio::test]
+async fn test_protocol_negotiation() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Newer clients are negotiated down to the server version
+    let mut client = server.client().await.expect("Failed to connect client");
+    client
+        .send(RemoteInMessage::Hello {
+            protocol_version: PROTOCOL_VERSION + 1,
+            client_name: Some("future-client".to_string()),
+            capabilities: vec![Capability::Unknown],
+        })
+        .await
+        .expect("Failed to send Hello");
+    let msg = client.recv().await.expect("Failed to receive response");
+    match msg {
+        RemoteOutMessage::Welcome {
+            protocol_version,
+            capabilities,
+            ..
+        } => {
+            assert_eq!(protocol_version, PROTOCOL_VERSION);
+            assert!(
+                capabilities.is_empty(),
+                "Unknown capabilities are never enabled"
+            );
+        }
+        other => panic!("Expected Welcome message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_reject_unsupported_protocol_version() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    let mut client = server.client().await.expect("Failed to connect client");
+    client
+        .send(RemoteInMessage::Hello {
+            protocol_version: MIN_PROTOCOL_VERSION - 1,
+            client_name: None,
+            capabilities: vec![],
+        })
+        .await
+        .expect("Failed to send Hello");
+
+    let msg = client.recv().await.expect("Failed to receive response");
+    assert_matches!(msg, RemoteOutMessage::Reject { .. });
+}
+
+#[tokio::test]
+async fn test_legacy_client() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // A client of the original protocol sends no version
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1
+        .send(RemoteInMessage::Hello {
+            protocol_version: MIN_PROTOCOL_VERSION,
+            client_name: None,
+            capabilities: vec![],
+        })
+        .await
+        .expect("Failed to send Hello");
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Welcome {
+            protocol_version: MIN_PROTOCOL_VERSION,
+            ..
+        }
+    );
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Messages of later versions are not sent, the first one is the turn of the client
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // Results of later versions are converted
+    client2.send(RemoteInMessage::Resign).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished {
+            result: GameResult::Finished {
+                winner: Player::Player1,
+                ..
+            }
+        }
+    );
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Disconnect { reason: None }
+    );
+}
+
+#[tokio::test]
+async fn test_json_encoding() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Newline-delimited JSON, as typed in netcat
+    let stream = TcpStream::connect(&server.address)
+        .await
+        .expect("Failed to connect client");
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    write
+        .write_all(b"{\"type\": \"hello\", \"protocol_version\": 6, \"client_name\": \"netcat\"}\n")
+        .await
+        .expect("Failed to send Hello");
+
+    let line = lines
+        .next_line()
+        .await
+        .expect("Failed to receive response")
+        .expect("Connection closed");
+    let msg: RemoteOutMessage = serde_json::from_str(&line).expect("Response is not JSON");
+    assert_matches!(msg, RemoteOutMessage::Welcome { .. });
+
+    // The session continues in JSON
+    let line = lines
+        .next_line()
+        .await
+        .expect("Failed to receive GameState")
+        .expect("Connection closed");
+    let msg: RemoteOutMessage = serde_json::from_str(&line).expect("Response is not JSON");
+    assert_matches!(msg, RemoteOutMessage::GameState { .. });
+
+    // CBOR clients can still use the same listener
+    let mut client = server.client().await.expect("Failed to connect client 2");
+    client.send(hello()).await.expect("Failed to send Hello");
+    assert_matches!(
+        client.recv().await.expect("Failed to receive Welcome"),
+        RemoteOutMessage::Welcome { .. }
+    );
+}
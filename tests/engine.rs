@@ -0,0 +1,68 @@
+use std::process::Command;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+#[test]
+fn test_engine_match() {
+    let archive_dir = std::env::temp_dir().join(format!("sternhalma-engines-{}", Uuid::new_v4()));
+    let engine = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/engines/first-move.sh");
+
+    // A single command line runs a full match between two engines
+    let status = Command::new(env!("CARGO_BIN_EXE_sternhalma-server"))
+        .args(["--engine", engine, "--engine", engine, "--max-turns", "20"])
+        .arg("--archive-dir")
+        .arg(&archive_dir)
+        .env("RUST_LOG", "debug")
+        .status()
+        .expect("Failed to run server");
+    assert!(status.success());
+
+    let index = std::fs::read_to_string(archive_dir.join("index.jsonl")).unwrap();
+    let entry: Value = serde_json::from_str(index.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["result"], "max_turns");
+    assert_eq!(entry["total_turns"], 20);
+    assert_eq!(entry["seats"][0]["name"], "first-move");
+    assert_eq!(entry["seats"][1]["name"], "first-move");
+
+    let _ = std::fs::remove_dir_all(archive_dir);
+}
+
+#[test]
+fn test_text_engine_match() {
+    let archive_dir = std::env::temp_dir().join(format!("sternhalma-engines-{}", Uuid::new_v4()));
+    let engine = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/engines/opening.sh");
+
+    // Both engines see themselves as Player 1, so the same opening is available to each of them
+    let status = Command::new(env!("CARGO_BIN_EXE_sternhalma-server"))
+        .args(["--text-engine", engine, "--text-engine", engine])
+        .args(["--engine-movetime", "10", "--max-turns", "2"])
+        .arg("--archive-dir")
+        .arg(&archive_dir)
+        .env("RUST_LOG", "debug")
+        .status()
+        .expect("Failed to run server");
+    assert!(status.success());
+
+    let index = std::fs::read_to_string(archive_dir.join("index.jsonl")).unwrap();
+    let entry: Value = serde_json::from_str(index.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["result"], "max_turns");
+    assert_eq!(entry["total_turns"], 2);
+    assert_eq!(entry["seats"][0]["name"], "opening");
+
+    // The game record holds the opening of both players, in absolute coordinates
+    let record = std::fs::read_to_string(
+        archive_dir.join(format!("{}.json", entry["id"].as_str().unwrap())),
+    )
+    .unwrap();
+    let record: Value = serde_json::from_str(&record).unwrap();
+    assert_eq!(
+        record["history"],
+        serde_json::json!([
+            ["player1", [[12, 4], [11, 4]]],
+            ["player2", [[4, 12], [5, 12]]]
+        ])
+    );
+
+    let _ = std::fs::remove_dir_all(archive_dir);
+}
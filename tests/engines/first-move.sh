@@ -0,0 +1,9 @@
+#!/bin/sh
+# Minimal engine speaking the JSON protocol over stdin/stdout: it always plays the first valid movement
+echo '{"type":"hello","protocol_version":5,"client_name":"first-move"}'
+while read -r line; do
+    case "$line" in
+        *'"type":"turn"'*) echo '{"type":"choice","movement_index":0}' ;;
+        *'"type":"disconnect"'*) exit 0 ;;
+    esac
+done
@@ -0,0 +1,10 @@
+#!/bin/sh
+# Minimal engine speaking the text protocol: it always plays the same opening movement
+while read -r line; do
+    case "$line" in
+        sternhalma) echo "id name opening"; echo "sternhalmaok" ;;
+        isready) echo "readyok" ;;
+        go*) echo "info depth 0"; echo "bestmove m5l5" ;;
+        quit) exit 0 ;;
+    esac
+done
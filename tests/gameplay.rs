@@ -1,5 +1,8 @@
-use common::TestServer;
-use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use assert_matches::assert_matches;
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::{
+    Capability, ErrorCode, Opponent, PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage,
+};
 use sternhalma_server::sternhalma::board::player::Player;
 
 mod common;
@@ -10,19 +13,56 @@ async fn test_gameplay_turn_and_move() {
 
     // Connect Player 1
     let mut client1 = server.client().await.expect("Failed to connect client 1");
-    client1
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello 1");
+    client1.send(hello()).await.expect("Failed to send Hello 1");
     let _welcome1 = client1.recv().await.expect("Failed to receive Welcome 1");
+    let _state1 = client1
+        .recv_game_state()
+        .await
+        .expect("Failed to receive GameState 1");
 
     // Connect Player 2
     let mut client2 = server.client().await.expect("Failed to connect client 2");
-    client2
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello 2");
+    client2.send(hello()).await.expect("Failed to send Hello 2");
     let _welcome2 = client2.recv().await.expect("Failed to receive Welcome 2");
+    let _state2 = client2
+        .recv_game_state()
+        .await
+        .expect("Failed to receive GameState 2");
+    // Both players learn who moves first
+    let started1 = client1
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted 1");
+    let started2 = client2
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted 2");
+    match (started1, started2) {
+        (
+            RemoteOutMessage::GameStarted {
+                you_move_first: first1,
+                opponents,
+                max_turns,
+                ..
+            },
+            RemoteOutMessage::GameStarted {
+                you_move_first: first2,
+                ..
+            },
+        ) => {
+            assert!(first1, "Player 1 moves first");
+            assert!(!first2, "Player 2 moves second");
+            assert_eq!(
+                opponents,
+                vec![Opponent {
+                    player: Player::Player2,
+                    name: Some("test-client".to_string()),
+                }]
+            );
+            assert_eq!(max_turns, Some(100));
+        }
+        (m1, m2) => panic!("Expected GameStarted messages, got: {:?} and {:?}", m1, m2),
+    }
 
     // Player 1 should receive Turn
     // Note: It might take a moment for game to start after players connect
@@ -99,3 +139,208 @@ async fn test_gameplay_turn_and_move() {
         other => panic!("Player 2 expected Turn message, got {:?}", other),
     };
 }
+
+#[tokio::test]
+async fn test_rejected_choices_report_errors() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Connect both players
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    let movements = match client1
+        .recv()
+        .await
+        .expect("Player 1 failed to receive Turn")
+    {
+        RemoteOutMessage::Turn { movements } => movements,
+        other => panic!("Expected Turn message for Player 1, got {:?}", other),
+    };
+
+    // Player 2 tries to move out of turn
+    client2
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .expect("Failed to send Choice");
+    let msg = client2
+        .recv()
+        .await
+        .expect("Player 2 failed to receive Error");
+    assert_matches!(
+        msg,
+        RemoteOutMessage::Error {
+            code: ErrorCode::NotYourTurn,
+            ..
+        }
+    );
+
+    // Player 1 picks a movement that does not exist
+    client1
+        .send(RemoteInMessage::Choice {
+            movement_index: movements.len(),
+        })
+        .await
+        .expect("Failed to send Choice");
+    let msg = client1
+        .recv()
+        .await
+        .expect("Player 1 failed to receive Error");
+    assert_matches!(
+        msg,
+        RemoteOutMessage::Error {
+            code: ErrorCode::InvalidMovementIndex,
+            ..
+        }
+    );
+
+    // The turn is still open for Player 1
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .expect("Failed to send Choice");
+    let msg = client1
+        .recv()
+        .await
+        .expect("Player 1 failed to receive Movement");
+    assert_matches!(msg, RemoteOutMessage::Movement { .. });
+}
+
+/// Hello message requesting movements by coordinates
+fn coordinate_hello() -> RemoteInMessage {
+    RemoteInMessage::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        client_name: None,
+        capabilities: vec![Capability::CoordinateMoves],
+    }
+}
+
+#[tokio::test]
+async fn test_moves_by_coordinates() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Connect both players
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(coordinate_hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(coordinate_hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    let movements = match client1
+        .recv()
+        .await
+        .expect("Player 1 failed to receive Turn")
+    {
+        RemoteOutMessage::Turn { movements } => movements,
+        other => panic!("Expected Turn message for Player 1, got {:?}", other),
+    };
+
+    // Player 1 moves by start and end coordinates
+    let [from, to] = *movements.last().unwrap();
+    client1
+        .send(RemoteInMessage::Move { from, to })
+        .await
+        .expect("Failed to send Move");
+    match client1
+        .recv()
+        .await
+        .expect("Player 1 failed to receive Movement")
+    {
+        RemoteOutMessage::Movement { movement, .. } => assert_eq!(movement, [from, to]),
+        other => panic!("Expected Movement message, got {:?}", other),
+    }
+    assert_matches!(
+        client2
+            .recv()
+            .await
+            .expect("Player 2 failed to receive Movement"),
+        RemoteOutMessage::Movement { .. }
+    );
+    assert_matches!(
+        client2
+            .recv()
+            .await
+            .expect("Player 2 failed to receive Turn"),
+        RemoteOutMessage::Turn { .. }
+    );
+
+    // Player 2 tries to hop without a piece to jump over
+    client2
+        .send(RemoteInMessage::MovePath {
+            path: vec![[12, 8], [10, 8]],
+        })
+        .await
+        .expect("Failed to send MovePath");
+    assert_matches!(
+        client2
+            .recv()
+            .await
+            .expect("Player 2 failed to receive Error"),
+        RemoteOutMessage::Error {
+            code: ErrorCode::InvalidMovement,
+            ..
+        }
+    );
+
+    // Player 2 hops over its own piece, in its own relative coordinates
+    let path = vec![[13, 4], [11, 4]];
+    client2
+        .send(RemoteInMessage::MovePath { path })
+        .await
+        .expect("Failed to send MovePath");
+    match client2
+        .recv()
+        .await
+        .expect("Player 2 failed to receive Movement")
+    {
+        RemoteOutMessage::Movement {
+            player, movement, ..
+        } => {
+            assert_eq!(player, Player::Player1);
+            assert_eq!(movement, [[13, 4], [11, 4]]);
+        }
+        other => panic!("Expected Movement message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_moves_by_coordinates_require_capability() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    assert_matches!(
+        client.recv().await.unwrap(),
+        RemoteOutMessage::Welcome { capabilities, .. } if capabilities.is_empty()
+    );
+    client.recv_game_state().await.unwrap();
+
+    client
+        .send(RemoteInMessage::Move {
+            from: [13, 4],
+            to: [12, 4],
+        })
+        .await
+        .expect("Failed to send Move");
+    assert_matches!(
+        client.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::UnexpectedMessage,
+            ..
+        }
+    );
+}
@@ -0,0 +1,505 @@
+use assert_matches::assert_matches;
+use common::{TestServer, hello, start_game};
+use serde_json::{Value, json};
+use sternhalma_server::server::protocol::{
+    CHAT_MESSAGE_LENGTH, MIN_PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage,
+};
+
+mod common;
+
+/// Starts a server with the HTTP listener enabled, along with the given arguments
+///
+/// Returns the server along with the base URL of the HTTP listener.
+fn http_server(args: &[&str]) -> (TestServer, String) {
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to find port");
+        listener.local_addr().unwrap().port()
+    };
+    let address = format!("127.0.0.1:{port}");
+    let server = TestServer::with_args(&[&["--ws", address.as_str()], args].concat())
+        .expect("Failed to start server");
+    (server, format!("http://{address}"))
+}
+
+/// Reader of a Server-Sent Events stream
+struct EventReader {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl EventReader {
+    /// Reads the next event, skipping keep-alive comments
+    ///
+    /// Returns the name, id and JSON data of the event.
+    async fn next(&mut self) -> (String, Option<String>, Value) {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let block: String = self.buffer.drain(..end + 2).collect();
+                let (mut name, mut id, mut data) = (None, None, None);
+                for line in block.lines() {
+                    if let Some(value) = line.strip_prefix("event: ") {
+                        name = Some(value.to_string());
+                    } else if let Some(value) = line.strip_prefix("id: ") {
+                        id = Some(value.to_string());
+                    } else if let Some(value) = line.strip_prefix("data: ") {
+                        data = Some(serde_json::from_str(value).expect("Invalid event data"));
+                    }
+                }
+                if let (Some(name), Some(data)) = (name, data) {
+                    return (name, id, data);
+                }
+                continue;
+            }
+            let chunk =
+                tokio::time::timeout(std::time::Duration::from_secs(5), self.response.chunk())
+                    .await
+                    .expect("Timed out waiting for event")
+                    .expect("Failed to read events")
+                    .expect("Event stream ended");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_list_and_get_games() {
+    let (server, url) = http_server(&[]);
+    let http = reqwest::Client::new();
+
+    // The default room is listed while waiting for players
+    let games: Value = http
+        .get(format!("{url}/games"))
+        .send()
+        .await
+        .expect("Failed to list games")
+        .json()
+        .await
+        .unwrap();
+    let games = games.as_array().expect("Games should be a list");
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0]["status"], "waiting");
+    let id = games[0]["id"].as_str().unwrap().to_string();
+
+    // A player joins the default room
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    client.recv().await.unwrap();
+    client.recv_game_state().await.unwrap();
+
+    let game: Value = http
+        .get(format!("{url}/games/{id}"))
+        .send()
+        .await
+        .expect("Failed to get game")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(game["id"], id);
+    assert_eq!(game["status"], "waiting");
+    assert_eq!(game["board"].as_array().unwrap().len(), 30);
+    assert_eq!(game["turn"], 0);
+    assert_eq!(
+        game["seats"],
+        json!([{ "player": "player1", "name": "test-client", "connected": true }])
+    );
+
+    // The record of the game is empty so far
+    let record: Value = http
+        .get(format!("{url}/games/{id}/record"))
+        .send()
+        .await
+        .expect("Failed to get record")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(record["variant"], "classic");
+    assert_eq!(record["max_turns"], 100);
+    assert_eq!(record["movements"], json!([]));
+    assert_eq!(record["result"], Value::Null);
+
+    // Unknown games are reported as such
+    let response = http
+        .get(format!("{url}/games/{}", uuid::Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // The live feed reports them with the same error format
+    let response = http
+        .get(format!("{url}/games/{}/events", uuid::Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let error: Value = response.json().await.unwrap();
+    assert!(error["error"].as_str().unwrap().contains("not found"));
+}
+
+#[tokio::test]
+async fn test_create_game() {
+    let (_server, url) = http_server(&[]);
+    let http = reqwest::Client::new();
+
+    let response = http
+        .post(format!("{url}/games"))
+        .json(&json!({ "max_turns": 10, "mute_chat": true }))
+        .send()
+        .await
+        .expect("Failed to create game");
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let created: Value = response.json().await.unwrap();
+    let id = created["id"].as_str().unwrap().to_string();
+    assert_eq!(created["ws"], format!("/games/{id}/ws"));
+
+    // The new room is listed after the default one, with its own settings
+    let games: Value = http
+        .get(format!("{url}/games"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(games.as_array().unwrap().len(), 2);
+    assert_eq!(games[1]["id"], id);
+
+    let record: Value = http
+        .get(format!("{url}/games/{id}/record"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(record["max_turns"], 10);
+
+    // Unknown settings are rejected
+    let response = http
+        .post(format!("{url}/games"))
+        .json(&json!({ "board_size": 3 }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+}
+
+#[tokio::test]
+async fn test_max_rooms() {
+    // The default room counts towards the limit
+    let (_server, url) = http_server(&["--max-rooms", "2"]);
+    let http = reqwest::Client::new();
+
+    let response = http
+        .post(format!("{url}/games"))
+        .json(&json!({ "timeout": 1, "persistent": true }))
+        .send()
+        .await
+        .expect("Failed to create game");
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let created: Value = response.json().await.unwrap();
+    let id = created["id"].as_str().unwrap().to_string();
+
+    // No more rooms can be created
+    let response = http.post(format!("{url}/games")).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let error: Value = response.json().await.unwrap();
+    assert!(error["error"].is_string());
+
+    // The persistent room closes once its lobby stayed empty, and is removed
+    let mut removed = false;
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let response = http.get(format!("{url}/games/{id}")).send().await.unwrap();
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            removed = true;
+            break;
+        }
+    }
+    assert!(removed, "Idle room should be removed");
+
+    // Which makes room for a new one
+    let response = http.post(format!("{url}/games")).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_live_feed() {
+    let (server, url) = http_server(&[]);
+    let http = reqwest::Client::new();
+
+    let games: Value = http
+        .get(format!("{url}/games"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = games[0]["id"].as_str().unwrap().to_string();
+
+    // Start a game and play a first movement
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    let RemoteOutMessage::Turn { movements } = client1.recv().await.unwrap() else {
+        panic!("Expected Turn for Player 1");
+    };
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+
+    // The feed replays the movement played before subscribing, in absolute coordinates
+    let response = http
+        .get(format!("{url}/games/{id}/events"))
+        .send()
+        .await
+        .expect("Failed to open feed");
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut events = EventReader {
+        response,
+        buffer: String::new(),
+    };
+    let (name, event_id, data) = events.next().await;
+    assert_eq!(name, "movement");
+    assert_eq!(event_id.as_deref(), Some("1"));
+    assert_eq!(data["turn"], 1);
+    assert_eq!(data["player"], "player1");
+    assert_eq!(data["movement"], json!(movements[0]));
+
+    // New movements are streamed live
+    let RemoteOutMessage::Turn { movements } = client2.recv().await.unwrap() else {
+        panic!("Expected Turn for Player 2");
+    };
+    client2
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    let (name, event_id, data) = events.next().await;
+    assert_eq!(name, "movement");
+    assert_eq!(event_id.as_deref(), Some("2"));
+    assert_eq!(data["player"], "player2");
+    let absolute = movements[0].map(|idx| idx.map(|c| 16 - c));
+    assert_eq!(data["movement"], json!(absolute));
+
+    // The feed ends with the result of the game
+    client1.send(RemoteInMessage::Resign).await.unwrap();
+    let (name, _, data) = events.next().await;
+    assert_eq!(name, "game_finished");
+    assert_eq!(data["result"]["type"], "resigned");
+    assert_eq!(data["result"]["winner"], "player2");
+}
+
+#[tokio::test]
+async fn test_live_feed_of_persistent_room() {
+    let (server, url) = http_server(&["--persistent"]);
+    let http = reqwest::Client::new();
+
+    let games: Value = http
+        .get(format!("{url}/games"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = games[0]["id"].as_str().unwrap().to_string();
+
+    let (mut client1, mut client2) = start_game(&server).await;
+    let response = http
+        .get(format!("{url}/games/{id}/events"))
+        .send()
+        .await
+        .expect("Failed to open feed");
+    let mut events = EventReader {
+        response,
+        buffer: String::new(),
+    };
+
+    // The first game ends, but not the feed
+    client2.send(RemoteInMessage::Resign).await.unwrap();
+    let (name, _, data) = events.next().await;
+    assert_eq!(name, "game_finished");
+    assert_eq!(data["result"]["winner"], "player1");
+
+    // Both players ask for a rematch, which is announced on the feed
+    client1.send(RemoteInMessage::Rematch).await.unwrap();
+    client2.send(RemoteInMessage::Rematch).await.unwrap();
+    let (name, _, data) = events.next().await;
+    assert_eq!(name, "new_game");
+    let game_id = data["id"].as_str().unwrap().to_string();
+    assert_ne!(game_id, id);
+
+    let game: Value = http
+        .get(format!("{url}/games/{id}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(game["game_id"], game_id);
+
+    // The movements of the rematch are numbered from the start again
+    let movements = loop {
+        if let RemoteOutMessage::Turn { movements } = client2.recv().await.unwrap() {
+            break movements;
+        }
+    };
+    client2
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    let (name, event_id, data) = events.next().await;
+    assert_eq!(name, "movement");
+    assert_eq!(event_id.as_deref(), Some("1"));
+    // Client 2 took the seat of Player 1, and sees the board from its own side
+    assert_eq!(data["player"], "player1");
+    assert_eq!(data["movement"], json!(movements[0]));
+}
+
+#[tokio::test]
+async fn test_spectator_chat() {
+    let (server, url) = http_server(&[]);
+    let http = reqwest::Client::new();
+
+    let games: Value = http
+        .get(format!("{url}/games"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = games[0]["id"].as_str().unwrap().to_string();
+
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    client.recv().await.unwrap();
+    client.recv_game_state().await.unwrap();
+
+    let response = http
+        .get(format!("{url}/games/{id}/events"))
+        .send()
+        .await
+        .expect("Failed to open feed");
+    let mut events = EventReader {
+        response,
+        buffer: String::new(),
+    };
+
+    // A spectator message is streamed on the live feed
+    let response = http
+        .post(format!("{url}/games/{id}/chat"))
+        .json(&json!({ "text": "go blue" }))
+        .send()
+        .await
+        .expect("Failed to send chat message");
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let (name, _, data) = events.next().await;
+    assert_eq!(name, "chat");
+    assert_eq!(data["text"], "go blue");
+    assert!(data["timestamp"].as_u64().unwrap() > 0);
+
+    // But never reaches the players, who only receive their own chat
+    client
+        .send(RemoteInMessage::Chat {
+            text: "hello".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_matches!(
+        client.recv().await.unwrap(),
+        RemoteOutMessage::Chat { text, .. } if text == "hello"
+    );
+
+    // Oversized messages are rejected
+    let response = http
+        .post(format!("{url}/games/{id}/chat"))
+        .json(&json!({ "text": "a".repeat(CHAT_MESSAGE_LENGTH + 1) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // Spectators of a muted room cannot chat either
+    let created: Value = http
+        .post(format!("{url}/games"))
+        .json(&json!({ "mute_chat": true }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let response = http
+        .post(format!(
+            "{url}/games/{}/chat",
+            created["id"].as_str().unwrap()
+        ))
+        .json(&json!({ "text": "go red" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let (server, url) = http_server(&[]);
+    let http = reqwest::Client::new();
+
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    client.recv().await.unwrap();
+    client.recv_game_state().await.unwrap();
+
+    // A client with an unsupported protocol version is rejected
+    let mut rejected = server.client().await.expect("Failed to connect client");
+    rejected
+        .send(RemoteInMessage::Hello {
+            protocol_version: MIN_PROTOCOL_VERSION - 1,
+            client_name: None,
+            capabilities: vec![],
+        })
+        .await
+        .unwrap();
+    assert_matches!(
+        rejected.recv().await.unwrap(),
+        RemoteOutMessage::Reject { .. }
+    );
+    assert!(
+        rejected.recv().await.is_err(),
+        "Connection should be closed"
+    );
+
+    let response = http
+        .get(format!("{url}/metrics"))
+        .send()
+        .await
+        .expect("Failed to get metrics");
+    assert!(response.status().is_success());
+    let metrics = response.text().await.unwrap();
+    assert!(
+        metrics.contains("sternhalma_connections{transport=\"tcp\"} 1"),
+        "{metrics}"
+    );
+    assert!(
+        metrics.contains("sternhalma_handshake_rejects_total{reason=\"protocol\"} 1"),
+        "{metrics}"
+    );
+    assert!(metrics.contains("sternhalma_active_games 0"), "{metrics}");
+}
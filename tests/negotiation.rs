@@ -0,0 +1,199 @@
+use assert_matches::assert_matches;
+use common::{TestServer, start_game};
+use sternhalma_server::server::protocol::{ErrorCode, RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::sternhalma::{GameResult, board::player::Player};
+
+mod common;
+
+#[tokio::test]
+async fn test_resignation() {
+    let server = TestServer::new().expect("Failed to start server");
+    let (mut client1, mut client2) = start_game(&server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // Player 2 resigns while it is not their turn
+    client2
+        .send(RemoteInMessage::Resign)
+        .await
+        .expect("Failed to send Resign");
+
+    // Both players see the game won by Player 1
+    assert_matches!(
+        client1
+            .recv()
+            .await
+            .expect("Player 1 failed to receive result"),
+        RemoteOutMessage::GameFinished {
+            result: GameResult::Resigned {
+                winner: Player::Player1,
+                ..
+            }
+        }
+    );
+    assert_matches!(
+        client2
+            .recv()
+            .await
+            .expect("Player 2 failed to receive result"),
+        RemoteOutMessage::GameFinished {
+            result: GameResult::Resigned {
+                winner: Player::Player2,
+                ..
+            }
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_draw_offers() {
+    let server = TestServer::new().expect("Failed to start server");
+    let (mut client1, mut client2) = start_game(&server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // There is nothing to accept yet
+    client2.send(RemoteInMessage::AcceptDraw).await.unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::NoPendingOffer,
+            ..
+        }
+    );
+
+    // Player 1 offers a draw, which Player 2 declines
+    client1.send(RemoteInMessage::OfferDraw).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::DrawOffered {
+            player: Player::Player1
+        }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::DrawOffered {
+            player: Player::Player2
+        }
+    );
+
+    // Player 2 has to answer before making an offer of their own
+    client2.send(RemoteInMessage::OfferDraw).await.unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::OfferPending,
+            ..
+        }
+    );
+
+    client2.send(RemoteInMessage::DeclineDraw).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::DrawDeclined {
+            player: Player::Player2
+        }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::DrawDeclined { .. }
+    );
+
+    // Player 2 offers a draw, which Player 1 accepts
+    client2.send(RemoteInMessage::OfferDraw).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::DrawOffered { .. }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::DrawOffered { .. }
+    );
+    client1.send(RemoteInMessage::AcceptDraw).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished {
+            result: GameResult::Draw { total_turns: 0, .. }
+        }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished {
+            result: GameResult::Draw { .. }
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_takeback() {
+    let server = TestServer::new().expect("Failed to start server");
+    let (mut client1, mut client2) = start_game(&server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // Player 1 has nothing to take back before moving
+    client1
+        .send(RemoteInMessage::RequestTakeback)
+        .await
+        .unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Error {
+            code: ErrorCode::NothingToTakeBack,
+            ..
+        }
+    );
+
+    // Player 1 moves
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    let movement = match client1.recv().await.unwrap() {
+        RemoteOutMessage::Movement { movement, .. } => movement,
+        other => panic!("Expected Movement, got {:?}", other),
+    };
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+    assert_matches!(client2.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // Player 1 regrets it and Player 2 agrees
+    client1
+        .send(RemoteInMessage::RequestTakeback)
+        .await
+        .unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::TakebackRequested {
+            player: Player::Player1
+        }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::TakebackRequested {
+            player: Player::Player2
+        }
+    );
+    client2.send(RemoteInMessage::AcceptTakeback).await.unwrap();
+
+    // The movement is undone and Player 1 is to move again
+    match client1.recv().await.unwrap() {
+        RemoteOutMessage::TakenBack {
+            player,
+            movements,
+            scores,
+        } => {
+            assert_eq!(player, Player::Player1);
+            assert_eq!(movements, vec![movement]);
+            assert_eq!(scores, [0, 0]);
+        }
+        other => panic!("Expected TakenBack, got {:?}", other),
+    }
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::TakenBack {
+            player: Player::Player2,
+            ..
+        }
+    );
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+}
@@ -1,6 +1,7 @@
-use common::TestServer;
+use common::{TestServer, hello, join, reconnect};
 use std::mem::drop;
-use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::server::protocol::{PROTOCOL_VERSION, RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::sternhalma::{GameResult, board::player::Player};
 
 mod common;
 
@@ -10,24 +11,34 @@ async fn test_reconnection() {
 
     // Connect Client 1
     let mut client1 = server.client().await.expect("Failed to connect client 1");
-    client1
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello 1");
+    client1.send(hello()).await.expect("Failed to send Hello 1");
     let msg_welcome = client1.recv().await.expect("Failed to receive Welcome 1");
+    let _state1 = client1
+        .recv_game_state()
+        .await
+        .expect("Failed to receive GameState 1");
 
     let session_id = match msg_welcome {
-        RemoteOutMessage::Welcome { session_id } => session_id,
+        RemoteOutMessage::Welcome { session_id, .. } => session_id,
         other => panic!("Expected Welcome, got: {:?}", other),
     };
 
     // Connect Client 2 to start game
     let mut client2 = server.client().await.expect("Failed to connect client 2");
-    client2
-        .send(RemoteInMessage::Hello)
-        .await
-        .expect("Failed to send Hello 2");
+    client2.send(hello()).await.expect("Failed to send Hello 2");
     let _ = client2.recv().await.expect("Failed to receive Welcome 2");
+    let _ = client2
+        .recv_game_state()
+        .await
+        .expect("Failed to receive GameState 2");
+    let _ = client1
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted 1");
+    let _ = client2
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted 2");
 
     // Client 1 receives Turn (wait for it to be sure game started)
     let _turn = client1
@@ -44,7 +55,11 @@ async fn test_reconnection() {
         .await
         .expect("Failed to connect client 1 again");
     client1_new
-        .send(RemoteInMessage::Reconnect { session_id })
+        .send(RemoteInMessage::Reconnect {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        })
         .await
         .expect("Failed to send Reconnect");
 
@@ -56,6 +71,7 @@ async fn test_reconnection() {
     match msg {
         RemoteOutMessage::Welcome {
             session_id: new_sid,
+            ..
         } => {
             assert_eq!(session_id, new_sid, "Session ID should match");
         }
@@ -63,6 +79,33 @@ async fn test_reconnection() {
         other => panic!("Unexpected message after reconnect: {:?}", other),
     }
 
+    // Expect the full game state
+    match client1_new
+        .recv()
+        .await
+        .expect("Failed to receive GameState after reconnect")
+    {
+        RemoteOutMessage::GameState {
+            board,
+            to_move,
+            turn,
+            history,
+            ..
+        } => {
+            assert_eq!(board.len(), 30, "All pieces should be on the board");
+            assert_eq!(to_move, Some(Player::Player1));
+            assert_eq!(turn, 0);
+            assert!(history.is_empty());
+        }
+        other => panic!("Expected GameState after reconnect, got {:?}", other),
+    }
+
+    // The game settings are announced again
+    client1_new
+        .recv_game_started()
+        .await
+        .expect("Failed to receive GameStarted after reconnect");
+
     // Should receive Turn again?
     // According to server logic, if it was My Turn, and I reconnect, server resends Turn.
     let turn_again = client1_new
@@ -74,3 +117,179 @@ async fn test_reconnection() {
         other => panic!("Expected Turn after reconnect, got {:?}", other),
     }
 }
+
+#[tokio::test]
+async fn test_forfeit_after_disconnect_timeout() {
+    let server =
+        TestServer::with_args(&["--disconnect-timeout", "1"]).expect("Failed to start server");
+
+    // Connect both players
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Wait for the game to start
+    let _turn = client1
+        .recv()
+        .await
+        .expect("Client 1 failed to receive Turn");
+
+    // Client 1 leaves and never comes back
+    drop(client1);
+
+    // Client 2 is told about the disconnection
+    let msg = client2
+        .recv()
+        .await
+        .expect("Failed to receive disconnection notice");
+    match msg {
+        RemoteOutMessage::OpponentDisconnected {
+            player,
+            timeout_secs,
+        } => {
+            assert_eq!(player, Player::Player2);
+            assert_eq!(timeout_secs, 1);
+        }
+        other => panic!("Expected OpponentDisconnected, got {:?}", other),
+    }
+
+    // Client 2 wins by forfeit once the grace period expires
+    let msg = client2.recv().await.expect("Failed to receive game result");
+    match msg {
+        RemoteOutMessage::GameFinished {
+            result: GameResult::Forfeit { winner, .. },
+        } => assert_eq!(winner, Player::Player1),
+        other => panic!("Expected forfeit GameFinished, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_game_state_after_reconnect() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Connect both players
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    let session_id = match client2.recv().await.unwrap() {
+        RemoteOutMessage::Welcome { session_id, .. } => session_id,
+        other => panic!("Expected Welcome, got: {:?}", other),
+    };
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Player 1 moves
+    let movements = match client1.recv().await.unwrap() {
+        RemoteOutMessage::Turn { movements } => movements,
+        other => panic!("Expected Turn, got {:?}", other),
+    };
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    client2.recv().await.unwrap();
+
+    // Player 2 drops and comes back
+    drop(client2);
+    let mut client2 = server.client().await.expect("Failed to reconnect client 2");
+    client2
+        .send(RemoteInMessage::Reconnect {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        })
+        .await
+        .unwrap();
+    client2.recv().await.unwrap();
+
+    // The missed movement is part of the history, in Player 2's perspective
+    match client2.recv_game_state().await.unwrap() {
+        RemoteOutMessage::GameState {
+            board,
+            to_move,
+            turn,
+            history,
+            ..
+        } => {
+            let relative_move = movements[0].map(|idx| idx.map(|c| 17 - 1 - c));
+            assert_eq!(to_move, Some(Player::Player1));
+            assert_eq!(turn, 1);
+            assert_eq!(history, vec![(Player::Player2, relative_move)]);
+            assert!(board.contains(&(relative_move[1], Player::Player2)));
+            assert!(!board.iter().any(|(idx, _)| *idx == relative_move[0]));
+        }
+        other => panic!("Expected GameState, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_reconnection_before_game_starts() {
+    let server = TestServer::new().expect("Failed to start server");
+
+    // Client 1 leaves before an opponent arrives
+    let (client1, session_id) = join(&server).await;
+    drop(client1);
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    // Its seat is held, so a new client takes the other one
+    let (mut client2, _) = join(&server).await;
+
+    // Client 1 comes back with its session and the game starts
+    let mut client1 = reconnect(&server, session_id).await;
+    client1.recv_game_state().await.unwrap();
+    match client1.recv_game_started().await.unwrap() {
+        RemoteOutMessage::GameStarted { you_move_first, .. } => assert!(you_move_first),
+        _ => unreachable!(),
+    }
+    // Client 2 joined after client 1 left, and is told it came back
+    match client2.recv().await.unwrap() {
+        RemoteOutMessage::OpponentReconnected { .. } => {}
+        other => panic!("Expected OpponentReconnected, got: {:?}", other),
+    }
+    client2.recv_game_started().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_seat_freed_after_leaving_before_game_starts() {
+    let server =
+        TestServer::with_args(&["--disconnect-timeout", "1"]).expect("Failed to start server");
+
+    // Client 1 leaves before an opponent arrives and does not come back in time
+    let (client1, session_id) = join(&server).await;
+    drop(client1);
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+
+    // Its session is forgotten
+    let mut client = server.client().await.expect("Failed to connect client");
+    client
+        .send(RemoteInMessage::Reconnect {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![],
+        })
+        .await
+        .unwrap();
+    match client.recv().await.unwrap() {
+        RemoteOutMessage::Reject { .. } => {}
+        other => panic!("Expected Reject, got: {:?}", other),
+    }
+
+    // And its seat is given to new clients
+    let (mut client2, _) = join(&server).await;
+    let (mut client3, _) = join(&server).await;
+    client2.recv_game_started().await.unwrap();
+    client3.recv_game_started().await.unwrap();
+}
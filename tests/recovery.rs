@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use common::{TestServer, hello, join, reconnect, start_game};
+use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::sternhalma::board::player::Player;
+use uuid::Uuid;
+
+mod common;
+
+#[tokio::test]
+async fn test_recovery_after_restart() {
+    let journal_dir = std::env::temp_dir().join(format!("sternhalma-journal-{}", Uuid::new_v4()));
+    let journal_arg = journal_dir.to_str().unwrap().to_string();
+
+    let server =
+        TestServer::with_args(&["--journal-dir", &journal_arg]).expect("Failed to start server");
+    let (mut client1, session1) = join(&server).await;
+    let (mut client2, session2) = join(&server).await;
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Player 1 plays a movement
+    let RemoteOutMessage::Turn { movements } = client1.recv().await.unwrap() else {
+        panic!("Expected Turn for Player 1");
+    };
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+
+    // The server goes down without closing the game
+    drop(server);
+    drop(client1);
+    drop(client2);
+
+    // The restarted server resumes the game from its journal
+    let server =
+        TestServer::with_args(&["--journal-dir", &journal_arg]).expect("Failed to restart server");
+    let mut client1 = reconnect(&server, session1).await;
+    match client1.recv_game_state().await.unwrap() {
+        RemoteOutMessage::GameState {
+            to_move,
+            turn,
+            history,
+            ..
+        } => {
+            assert_eq!(to_move, Some(Player::Player2));
+            assert_eq!(turn, 1);
+            assert_eq!(history, vec![(Player::Player1, movements[0])]);
+        }
+        other => panic!("Expected GameState, got {:?}", other),
+    }
+
+    // Player 2 gets its turn back once reconnected
+    let mut client2 = reconnect(&server, session2).await;
+    client2.recv_game_state().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+    assert_matches!(client2.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // New players cannot take the seats of the recovered game
+    let mut intruder = server.client().await.expect("Failed to connect client");
+    intruder.send(hello()).await.unwrap();
+    assert_matches!(
+        intruder.recv().await.unwrap(),
+        RemoteOutMessage::Reject { .. }
+    );
+
+    drop(server);
+    let _ = std::fs::remove_dir_all(journal_dir);
+}
+
+#[tokio::test]
+async fn test_recovery_after_graceful_shutdown() {
+    let journal_dir = std::env::temp_dir().join(format!("sternhalma-journal-{}", Uuid::new_v4()));
+    let journal_arg = journal_dir.to_str().unwrap().to_string();
+
+    let mut server =
+        TestServer::with_args(&["--journal-dir", &journal_arg]).expect("Failed to start server");
+    let (mut client1, session1) = join(&server).await;
+    let (mut client2, session2) = join(&server).await;
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Player 1 plays a movement
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+
+    // The server is stopped: players are disconnected without the game finishing
+    server.signal("TERM").expect("Failed to signal server");
+    for client in [&mut client1, &mut client2] {
+        loop {
+            match client.recv().await.unwrap() {
+                // Player 2 may have been told it is its turn before the signal
+                RemoteOutMessage::Turn { .. } => {}
+                RemoteOutMessage::Disconnect { reason } => {
+                    assert!(reason.is_some());
+                    break;
+                }
+                other => panic!("Expected Disconnect, got {:?}", other),
+            }
+        }
+    }
+    assert!(
+        server.wait_exit(Duration::from_secs(5)).unwrap(),
+        "Server should exit successfully"
+    );
+
+    // The restarted server resumes the game from its journal
+    let server =
+        TestServer::with_args(&["--journal-dir", &journal_arg]).expect("Failed to restart server");
+    let mut client1 = reconnect(&server, session1).await;
+    assert_matches!(
+        client1.recv_game_state().await.unwrap(),
+        RemoteOutMessage::GameState {
+            to_move: Some(Player::Player2),
+            turn: 1,
+            ..
+        }
+    );
+    let mut client2 = reconnect(&server, session2).await;
+    client2.recv_game_state().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+    assert_matches!(client2.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    drop(server);
+    let _ = std::fs::remove_dir_all(journal_dir);
+}
+
+#[tokio::test]
+async fn test_recovery_with_bot() {
+    let journal_dir = std::env::temp_dir().join(format!("sternhalma-journal-{}", Uuid::new_v4()));
+    let journal_arg = journal_dir.to_str().unwrap().to_string();
+    let args = ["--journal-dir", &journal_arg, "--bot", "random:2"];
+
+    let server = TestServer::with_args(&args).expect("Failed to start server");
+    let (mut client, session_id) = join(&server).await;
+    client.recv_game_started().await.unwrap();
+
+    // Player 1 plays a movement and the bot answers
+    assert_matches!(client.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+    client
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        assert_matches!(
+            client.recv().await.unwrap(),
+            RemoteOutMessage::Movement { .. }
+        );
+    }
+    assert_matches!(client.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // The server goes down without closing the game
+    drop(server);
+    drop(client);
+
+    // The restarted server puts the bot back in its seat instead of failing to seat it again
+    let server = TestServer::with_args(&args).expect("Failed to restart server");
+    let mut client = reconnect(&server, session_id).await;
+    assert_matches!(
+        client.recv_game_state().await.unwrap(),
+        RemoteOutMessage::GameState {
+            to_move: Some(Player::Player1),
+            turn: 2,
+            ..
+        }
+    );
+    client.recv_game_started().await.unwrap();
+    assert_matches!(client.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    // The bot keeps playing
+    client
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        assert_matches!(
+            client.recv().await.unwrap(),
+            RemoteOutMessage::Movement { .. }
+        );
+    }
+    assert_matches!(client.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    drop(server);
+    let _ = std::fs::remove_dir_all(journal_dir);
+}
+
+#[tokio::test]
+async fn test_journal_removed_after_game() {
+    let journal_dir = std::env::temp_dir().join(format!("sternhalma-journal-{}", Uuid::new_v4()));
+    let journal_arg = journal_dir.to_str().unwrap().to_string();
+    let journals = || std::fs::read_dir(&journal_dir).unwrap().count();
+
+    let mut server =
+        TestServer::with_args(&["--journal-dir", &journal_arg]).expect("Failed to start server");
+    let (mut client1, _client2) = start_game(&server).await;
+    assert_eq!(journals(), 1);
+
+    // Player 1 resigns, which ends the game and the server with it
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+    client1.send(RemoteInMessage::Resign).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished { .. }
+    );
+    assert!(
+        server.wait_exit(Duration::from_secs(5)).unwrap(),
+        "Server should exit successfully"
+    );
+
+    // The finished game has nothing left to recover
+    assert_eq!(journals(), 0);
+
+    let _ = std::fs::remove_dir_all(journal_dir);
+}
@@ -0,0 +1,101 @@
+use assert_matches::assert_matches;
+use common::{TestClient, TestServer, hello, start_game};
+use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::sternhalma::board::player::Player;
+
+mod common;
+
+/// Connects both players, waits for the game to start and has Player 2 resign
+async fn play_game(server: &TestServer) -> (TestClient, TestClient) {
+    let (mut client1, mut client2) = start_game(server).await;
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+
+    client2.send(RemoteInMessage::Resign).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished { .. }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::GameFinished { .. }
+    );
+
+    (client1, client2)
+}
+
+#[tokio::test]
+async fn test_rematch_swaps_seats() {
+    let server = TestServer::with_args(&["--persistent"]).expect("Failed to start server");
+    let (mut client1, mut client2) = play_game(&server).await;
+
+    // Player 1 asks for a rematch, Player 2 sees the request of their opponent
+    client1.send(RemoteInMessage::Rematch).await.unwrap();
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::RematchRequested {
+            player: Player::Player1
+        }
+    );
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::RematchRequested {
+            player: Player::Player2
+        }
+    );
+
+    // Player 2 accepts and the rematch starts on a fresh board with the seats swapped
+    client2.send(RemoteInMessage::Rematch).await.unwrap();
+    for (client, moves_first) in [(&mut client1, false), (&mut client2, true)] {
+        assert_matches!(
+            client.recv().await.unwrap(),
+            RemoteOutMessage::RematchRequested { .. }
+        );
+        match client.recv_game_state().await.unwrap() {
+            RemoteOutMessage::GameState { history, .. } => assert!(history.is_empty()),
+            _ => unreachable!(),
+        }
+        assert_matches!(
+            client.recv_game_started().await.unwrap(),
+            RemoteOutMessage::GameStarted { you_move_first, .. } if you_move_first == moves_first
+        );
+    }
+    assert_matches!(client2.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+}
+
+#[tokio::test]
+async fn test_return_to_lobby() {
+    let server = TestServer::with_args(&["--persistent"]).expect("Failed to start server");
+    let (mut client1, mut client2) = play_game(&server).await;
+
+    // Player 2 leaves their seat, Player 1 waits in the lobby for a new opponent
+    client2.send(RemoteInMessage::ReturnToLobby).await.unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Disconnect { reason: Some(_) }
+    );
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::OpponentLeft {
+            player: Player::Player2
+        }
+    );
+    match client1.recv_game_state().await.unwrap() {
+        RemoteOutMessage::GameState { history, .. } => assert!(history.is_empty()),
+        _ => unreachable!(),
+    }
+
+    // A new player takes the free seat and the next game starts
+    let mut client3 = server.client().await.expect("Failed to connect client 3");
+    client3.send(hello()).await.unwrap();
+    client3.recv().await.unwrap();
+    client3.recv_game_state().await.unwrap();
+    assert_matches!(
+        client1.recv_game_started().await.unwrap(),
+        RemoteOutMessage::GameStarted {
+            you_move_first: true,
+            ..
+        }
+    );
+    client3.recv_game_started().await.unwrap();
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use sternhalma_server::sternhalma::GameResult;
+
+mod common;
+
+#[tokio::test]
+async fn test_graceful_shutdown() {
+    let mut server = TestServer::new().expect("Failed to start server");
+
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    client1.recv().await.unwrap();
+    client1.recv_game_state().await.unwrap();
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // Player 1 plays a movement
+    assert_matches!(client1.recv().await.unwrap(), RemoteOutMessage::Turn { .. });
+    client1
+        .send(RemoteInMessage::Choice { movement_index: 0 })
+        .await
+        .unwrap();
+    assert_matches!(
+        client2.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+    assert_matches!(
+        client1.recv().await.unwrap(),
+        RemoteOutMessage::Movement { .. }
+    );
+
+    server.signal("TERM").expect("Failed to signal server");
+
+    // Both players learn that the game was aborted, and why they are disconnected
+    for client in [&mut client1, &mut client2] {
+        let mut message = client.recv().await.unwrap();
+        // Player 2 may have been told it is its turn before the signal
+        if matches!(message, RemoteOutMessage::Turn { .. }) {
+            message = client.recv().await.unwrap();
+        }
+        match message {
+            RemoteOutMessage::GameFinished { result } => {
+                assert_matches!(result, GameResult::Aborted { total_turns: 1, .. });
+            }
+            other => panic!("Expected GameFinished, got {:?}", other),
+        }
+        match client.recv().await.unwrap() {
+            RemoteOutMessage::Disconnect { reason } => assert!(reason.is_some()),
+            other => panic!("Expected Disconnect, got {:?}", other),
+        }
+        assert!(client.recv().await.is_err(), "Connection should be closed");
+    }
+
+    assert!(
+        server.wait_exit(Duration::from_secs(5)).unwrap(),
+        "Server should exit successfully"
+    );
+}
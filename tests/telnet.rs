@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::{RemoteInMessage, RemoteOutMessage};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{TcpStream, tcp::OwnedReadHalf},
+};
+
+mod common;
+
+/// Reads lines until one contains the given text, returning every line read
+async fn read_until(lines: &mut Lines<BufReader<OwnedReadHalf>>, text: &str) -> Vec<String> {
+    let mut read = Vec::new();
+    loop {
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .unwrap_or_else(|_| panic!("Timed out waiting for `{text}`, read {read:?}"))
+            .expect("Failed to read line")
+            .unwrap_or_else(|| panic!("Connection closed waiting for `{text}`, read {read:?}"));
+        let found = line.contains(text);
+        read.push(line);
+        if found {
+            return read;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_telnet_game() {
+    let telnet = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    };
+    let server = TestServer::with_args(&["--telnet", &telnet]).expect("Failed to start server");
+
+    // A human joins by typing their name
+    let (read, mut write) = TcpStream::connect(&telnet).await.unwrap().into_split();
+    let mut lines = BufReader::new(read).lines();
+    read_until(&mut lines, "Name").await;
+    write.write_all(b"alice\n").await.unwrap();
+    read_until(&mut lines, "Joined with session").await;
+
+    // Against a client of the raw protocol
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    client.recv().await.unwrap();
+    client.recv_game_state().await.unwrap();
+    match client.recv_game_started().await.unwrap() {
+        RemoteOutMessage::GameStarted { opponents, .. } => {
+            assert_eq!(opponents[0].name.as_deref(), Some("alice"));
+        }
+        _ => unreachable!(),
+    }
+    read_until(&mut lines, "Game started").await;
+
+    // The available movements are numbered
+    let turn = read_until(&mut lines, "Your turn").await;
+    assert!(!turn.is_empty());
+    let first = read_until(&mut lines, "1)").await;
+    assert!(first.last().unwrap().contains("->"));
+
+    // Unknown commands are explained without ending the session
+    write.write_all(b"dance\n").await.unwrap();
+    read_until(&mut lines, "Unknown command").await;
+
+    // Picking a movement by its number plays it
+    write.write_all(b"1\n").await.unwrap();
+    read_until(&mut lines, "moved").await;
+    match client.recv().await.unwrap() {
+        RemoteOutMessage::Movement { .. } => {}
+        other => panic!("Expected Movement, got {other:?}"),
+    }
+
+    // The opponent resigning ends the game
+    client.send(RemoteInMessage::Resign).await.unwrap();
+    read_until(&mut lines, "Game over").await;
+}
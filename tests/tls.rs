@@ -0,0 +1,169 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use assert_matches::assert_matches;
+use common::{TestServer, hello};
+use futures::{SinkExt, StreamExt};
+use rustls::pki_types::{CertificateDer, ServerName};
+use sternhalma_server::server::protocol::{ClientCodec, RemoteOutMessage};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_rustls::{TlsConnector, client::TlsStream};
+use tokio_util::codec::Framed;
+use uuid::Uuid;
+
+mod common;
+
+/// Self-signed certificate written to a temporary directory
+struct TestCertificate {
+    dir: PathBuf,
+    cert: CertificateDer<'static>,
+}
+
+impl TestCertificate {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("sternhalma-tls-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut certificate = Self {
+            dir,
+            cert: CertificateDer::from(vec![]),
+        };
+        certificate.renew();
+        certificate
+    }
+
+    /// Replaces the certificate with a new one
+    fn renew(&mut self) {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("Failed to generate certificate");
+        std::fs::write(self.cert_path(), generated.cert.pem()).unwrap();
+        std::fs::write(self.key_path(), generated.signing_key.serialize_pem()).unwrap();
+        self.cert = generated.cert.der().clone();
+    }
+
+    fn cert_path(&self) -> PathBuf {
+        self.dir.join("cert.pem")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.dir.join("key.pem")
+    }
+
+    /// Command line arguments serving this certificate
+    fn args(&self) -> Vec<String> {
+        vec![
+            "--tls-cert".to_string(),
+            self.cert_path().to_str().unwrap().to_string(),
+            "--tls-key".to_string(),
+            self.key_path().to_str().unwrap().to_string(),
+        ]
+    }
+}
+
+impl Drop for TestCertificate {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Opens a TLS connection trusting only the given certificate
+async fn connect(
+    address: &str,
+    trusted: &CertificateDer<'static>,
+) -> std::io::Result<TlsStream<TcpStream>> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(trusted.clone()).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let stream = TcpStream::connect(address).await?;
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+}
+
+/// Sends Hello over a TLS connection and returns the answer of the server
+async fn hello_over_tls(stream: TlsStream<TcpStream>) -> RemoteOutMessage {
+    let mut framed = Framed::new(stream, ClientCodec::new());
+    framed.send(hello()).await.unwrap();
+    framed
+        .next()
+        .await
+        .expect("Connection closed")
+        .expect("Failed to receive message")
+}
+
+#[tokio::test]
+async fn test_tls_tcp_listener() {
+    let certificate = TestCertificate::new();
+    let args = certificate.args();
+    let server = TestServer::with_args(&args.iter().map(String::as_str).collect::<Vec<_>>())
+        .expect("Failed to start server");
+
+    // The handshake runs over TLS
+    let stream = connect(&server.address, &certificate.cert)
+        .await
+        .expect("TLS connection failed");
+    assert_matches!(
+        hello_over_tls(stream).await,
+        RemoteOutMessage::Welcome { .. }
+    );
+
+    // Plaintext clients are not answered
+    let mut client = server.client().await.expect("Failed to connect client");
+    client.send(hello()).await.unwrap();
+    assert!(client.recv().await.is_err());
+}
+
+#[tokio::test]
+async fn test_tls_http_listener() {
+    let certificate = TestCertificate::new();
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to find port");
+        listener.local_addr().unwrap().port()
+    };
+    let address = format!("127.0.0.1:{port}");
+    let mut args = certificate.args();
+    args.extend(["--ws".to_string(), address.clone()]);
+    let _server = TestServer::with_args(&args.iter().map(String::as_str).collect::<Vec<_>>())
+        .expect("Failed to start server");
+
+    let mut stream = connect(&address, &certificate.cert)
+        .await
+        .expect("TLS connection failed");
+    stream
+        .write_all(b"GET /games HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+}
+
+#[tokio::test]
+async fn test_tls_certificate_reload() {
+    let mut certificate = TestCertificate::new();
+    let args = certificate.args();
+    let server = TestServer::with_args(&args.iter().map(String::as_str).collect::<Vec<_>>())
+        .expect("Failed to start server");
+    let previous = certificate.cert.clone();
+
+    // The renewed certificate is served once the server receives SIGHUP
+    certificate.renew();
+    server.signal("HUP").expect("Failed to signal server");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert!(connect(&server.address, &previous).await.is_err());
+    let stream = connect(&server.address, &certificate.cert)
+        .await
+        .expect("TLS connection failed");
+    assert_matches!(
+        hello_over_tls(stream).await,
+        RemoteOutMessage::Welcome { .. }
+    );
+}
@@ -0,0 +1,52 @@
+use std::{os::unix::fs::PermissionsExt, path::Path, process::Command, time::Duration};
+
+use common::{TestServer, hello};
+use sternhalma_server::server::protocol::RemoteOutMessage;
+
+mod common;
+
+#[tokio::test]
+async fn test_unix_socket_listener() {
+    let mut server =
+        TestServer::with_unix_socket(&["--unix-mode", "600"]).expect("Failed to start server");
+    let path = server.address.clone();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // The private directory the socket was bound in is removed
+    let socket = Path::new(&path);
+    let staging = format!(".{}.", socket.file_name().unwrap().to_str().unwrap());
+    let leftovers = std::fs::read_dir(socket.parent().unwrap())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&staging))
+        .count();
+    assert_eq!(leftovers, 0);
+
+    // Players join over the socket with the raw protocol
+    let mut client1 = server.client().await.expect("Failed to connect client 1");
+    client1.send(hello()).await.unwrap();
+    match client1.recv().await.unwrap() {
+        RemoteOutMessage::Welcome { .. } => {}
+        other => panic!("Expected Welcome, got {:?}", other),
+    }
+    client1.recv_game_state().await.unwrap();
+    let mut client2 = server.client().await.expect("Failed to connect client 2");
+    client2.send(hello()).await.unwrap();
+    client2.recv().await.unwrap();
+    client2.recv_game_state().await.unwrap();
+    client1.recv_game_started().await.unwrap();
+    client2.recv_game_started().await.unwrap();
+
+    // A second server cannot take over a socket in use
+    let status = Command::new(env!("CARGO_BIN_EXE_sternhalma-server"))
+        .args(["--unix", &path])
+        .status()
+        .expect("Failed to run server");
+    assert!(!status.success());
+
+    // The socket file is removed once the server exits
+    server.signal("TERM").expect("Failed to signal server");
+    assert!(server.wait_exit(Duration::from_secs(5)).unwrap());
+    assert!(!Path::new(&path).exists());
+}